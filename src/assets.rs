@@ -1,72 +1,98 @@
 use std::collections::HashMap;
 
-use asefile::AsepriteFile;
-use image::EncodableLayout;
+use asefile::{AnimationDirection, AsepriteFile};
+use image::{EncodableLayout, RgbaImage};
 use macroquad::prelude::*;
 
-use crate::utils::create_camera;
+use crate::{
+    atlas::{Atlas, AtlasBuilder},
+    manifest,
+    utils::{TILE_ANCHOR, create_camera},
+};
 
 // hello!
 
 pub struct Assets {
-    pub tileset: Spritesheet,
-    pub map: Map,
-    pub player: AnimationsGroup,
-    pub cups: AnimationsGroup,
-    pub vision_cones: Texture2D,
-    pub snow_blowing: Animation,
-    pub raincoat_ferret: AnimationsGroup,
-    pub mother_ferret: AnimationsGroup,
-    pub child_ferret: [AnimationsGroup; 2],
-    pub door: AnimationsGroup,
-    pub fireplace: AnimationsGroup,
-    pub font: Font,
-    pub screens: Vec<Animation>,
+    pub sprites: Sprites,
+    /// Textures of the atlas pages every sprite and screen is packed into
+    pub atlas_pages: Vec<Texture2D>,
+    pub maps: HashMap<&'static str, Map>,
+    pub fonts: HashMap<&'static str, Font>,
 }
 impl Assets {
     pub fn load() -> Self {
-        let tileset = Spritesheet::new(
-            load_ase_texture(include_bytes!("../assets/tileset.ase"), None),
-            16.0,
-        );
+        let tileset = Spritesheet::new(load_ase_texture(manifest::TILESET, None), 16.0);
+        let (sprites, atlas) = Sprites::load();
         Self {
-            map: Map::new(include_str!("../assets/map.tmx"), &tileset),
-            tileset,
-            player: AnimationsGroup::from_file(include_bytes!("../assets/player.ase")),
-            cups: AnimationsGroup::from_file(include_bytes!("../assets/cups.ase")),
-            vision_cones: load_ase_texture(include_bytes!("../assets/vision_cones.ase"), None),
-            snow_blowing: Animation::from_file(include_bytes!("../assets/snow_blowing.ase")),
-            raincoat_ferret: AnimationsGroup::from_file(include_bytes!(
-                "../assets/raincoat_ferret.ase"
-            )),
-            mother_ferret: AnimationsGroup::from_file(include_bytes!(
-                "../assets/mother_ferret.ase"
-            )),
-            child_ferret: [
-                AnimationsGroup::from_file(include_bytes!("../assets/child_ferret1.ase")),
-                AnimationsGroup::from_file(include_bytes!("../assets/child_ferret2.ase")),
-            ],
-            door: AnimationsGroup::from_file(include_bytes!("../assets/door.ase")),
-            fireplace: AnimationsGroup::from_file(include_bytes!("../assets/fireplace.ase")),
-            font: load_ttf_font_from_bytes(include_bytes!("../assets/tinos.ttf")).unwrap(),
-            screens: vec![
-                Animation::from_file(include_bytes!("../assets/screens/raincoat_ferret.ase")),
-                Animation::from_file(include_bytes!(
-                    "../assets/screens/raincoat_ferret_happy.ase"
-                )),
-                Animation::from_file(include_bytes!("../assets/screens/ferret_family.ase")),
-                Animation::from_file(include_bytes!("../assets/screens/ferret_family_happy.ase")),
-                Animation::from_file(include_bytes!(
-                    "../assets/screens/child_ferrets_hot_chocolate.ase"
-                )),
-            ],
+            sprites,
+            atlas_pages: atlas.upload(),
+            maps: manifest::MAPS
+                .iter()
+                .map(|(name, data)| (*name, Map::new(data, tileset.clone())))
+                .collect(),
+            fonts: manifest::FONTS
+                .iter()
+                .map(|(name, bytes)| (*name, load_ttf_font_from_bytes(bytes).unwrap()))
+                .collect(),
         }
     }
+    pub fn map(&self, name: &str) -> &Map {
+        get_named(&self.maps, "map", name)
+    }
+    pub fn sprite(&self, name: &str) -> &AnimationsGroup {
+        self.sprites.sprite(name)
+    }
+    pub fn font(&self, name: &str) -> &Font {
+        get_named(&self.fonts, "font", name)
+    }
+}
+
+/// Every sprite and screen, with where their frames are in the atlas. None of it lives on the GPU,
+/// so frames can be drawn into a `Recorder` without a window.
+pub struct Sprites {
+    pub groups: HashMap<&'static str, AnimationsGroup>,
+    pub screens: HashMap<&'static str, Animation>,
+    /// Page index and source rect of every frame's region
+    pub regions: Vec<(usize, Rect)>,
+}
+impl Sprites {
+    /// Reads every sprite and screen in the manifest, along with the atlas their frames were packed into
+    pub fn load() -> (Self, Atlas) {
+        let mut atlas = AtlasBuilder::default();
+        let groups = manifest::SPRITES
+            .iter()
+            .map(|(name, bytes)| (*name, AnimationsGroup::from_file(bytes, &mut atlas)))
+            .collect();
+        let screens = manifest::SCREENS
+            .iter()
+            .map(|(name, bytes)| (*name, Animation::from_file(bytes, &mut atlas)))
+            .collect();
+        let atlas = atlas.build();
+        let sprites = Self {
+            groups,
+            screens,
+            regions: atlas.regions.clone(),
+        };
+        (sprites, atlas)
+    }
+    pub fn sprite(&self, name: &str) -> &AnimationsGroup {
+        get_named(&self.groups, "sprite", name)
+    }
+    pub fn screen(&self, name: &str) -> &Animation {
+        get_named(&self.screens, "screen", name)
+    }
+}
+
+fn get_named<'a, T>(items: &'a HashMap<&'static str, T>, kind: &str, name: &str) -> &'a T {
+    items
+        .get(name)
+        .unwrap_or_else(|| panic!("no {kind} named {name:?} in the asset manifest"))
 }
 
 pub struct Map {
     pub background_camera: Camera2D,
-    pub foreground_camera: Camera2D,
+    /// Walls and detail are drawn tile by tile so they can be depth sorted with characters
+    pub tileset: Spritesheet,
     pub floor: TileMap,
     pub floor_decorations: TileMap,
     pub walls: TileMap,
@@ -74,17 +100,15 @@ pub struct Map {
     pub special: TileMap,
 }
 impl Map {
-    pub fn new(data: &str, tileset: &Spritesheet) -> Self {
+    pub fn new(data: &str, tileset: Spritesheet) -> Self {
         let floor = parse_tilemap_layer(data, "floor");
         let w = floor.1 as f32 * 16.0;
         let h = (floor.0.len() / floor.1) as f32 * 16.0;
         let mut background_camera = create_camera(w, h);
         background_camera.target = vec2(w / 2.0, h / 2.0);
-        let mut foreground_camera = create_camera(w, h);
-        foreground_camera.target = vec2(w / 2.0, h / 2.0);
         let new = Self {
             background_camera,
-            foreground_camera,
+            tileset,
             floor,
             floor_decorations: parse_tilemap_layer(data, "floor_decorations"),
             walls: parse_tilemap_layer(data, "walls"),
@@ -92,33 +116,64 @@ impl Map {
             special: parse_tilemap_layer(data, "special"),
         };
         set_camera(&new.background_camera);
-        new.floor.draw(tileset);
-        new.floor_decorations.draw(tileset);
-        set_camera(&new.foreground_camera);
-        new.walls.draw(tileset);
-        new.detail.draw(tileset);
+        new.floor.draw(&new.tileset);
+        new.floor_decorations.draw(&new.tileset);
         new
     }
+    /// A map with these walls and every other layer empty, for tests.
+    /// Nothing is uploaded for it, so it can't be drawn.
+    #[cfg(test)]
+    pub fn headless(walls: TileMap) -> Self {
+        let empty = TileMap(vec![0; walls.0.len()], walls.1);
+        let texture = miniquad::TextureId::from_raw_id(miniquad::RawId::OpenGl(0));
+        Self {
+            background_camera: Camera2D::default(),
+            tileset: Spritesheet::new(Texture2D::from_miniquad_texture(texture), 16.0),
+            floor: empty.clone(),
+            floor_decorations: empty.clone(),
+            walls,
+            detail: empty.clone(),
+            special: empty,
+        }
+    }
+    /// Area the map covers in the world
+    pub fn bounds(&self) -> Rect {
+        let (w, h) = (self.floor.1, self.floor.0.len() / self.floor.1);
+        Rect::new(0.0, 0.0, (w * 16) as f32, (h * 16) as f32)
+    }
+    /// Every wall and detail tile as (x, y, tile id), details after the walls under them
+    pub fn foreground_tiles(&self) -> impl Iterator<Item = (usize, usize, u8)> + '_ {
+        [&self.walls, &self.detail].into_iter().flat_map(|layer| {
+            layer
+                .0
+                .iter()
+                .enumerate()
+                .filter(|(_, tile)| **tile != 0)
+                .map(|(index, tile)| (index % layer.1, index / layer.1, *tile))
+        })
+    }
 }
 
+#[derive(Clone, PartialEq)]
 pub struct TileMap(pub Vec<u8>, pub usize);
 impl TileMap {
     fn draw(&self, tileset: &Spritesheet) {
-        let spritesheet_width = (tileset.texture.width() / tileset.sprite_size) as u8;
         for (index, tile) in self.0.iter().enumerate() {
             if *tile == 0 {
                 continue;
             }
-            let tile = tile - 1;
             let x = index % self.1;
             let y = index / self.1;
 
-            tileset.draw_tile(
+            draw_texture_ex(
+                &tileset.texture,
                 (x * 16) as f32,
                 (y * 16) as f32,
-                (tile % spritesheet_width) as f32,
-                (tile / spritesheet_width) as f32,
-                None,
+                WHITE,
+                DrawTextureParams {
+                    source: Some(tileset.tile_source(*tile)),
+                    ..Default::default()
+                },
             );
         }
     }
@@ -133,6 +188,27 @@ impl TileMap {
         }
         panic!()
     }
+    /// Positions of every tile with the given id, in reading order
+    pub fn find_tiles(&self, target: u8) -> Vec<(usize, usize)> {
+        self.0
+            .iter()
+            .enumerate()
+            .filter(|(_, tile)| **tile != 0 && *tile - 1 == target)
+            .map(|(index, _)| (index % self.1, index / self.1))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+impl TileMap {
+    /// A layer from rows of text, `#` for tile 1 and anything else for empty
+    pub fn from_rows(rows: &[&str]) -> Self {
+        let tiles = rows
+            .iter()
+            .flat_map(|row| row.chars().map(|f| (f == '#') as u8))
+            .collect();
+        TileMap(tiles, rows[0].len())
+    }
 }
 
 pub fn parse_tilemap_layer(xml: &str, layer_name: &str) -> TileMap {
@@ -157,14 +233,14 @@ pub fn parse_tilemap_layer(xml: &str, layer_name: &str) -> TileMap {
         .split_once("</data>")
         .unwrap()
         .0;
-    let mut split = xml.split(',');
     let mut data = vec![0];
-    while let Some(tile) = split.next() {
+    for tile in xml.split(',') {
         data.push(tile.trim().parse().unwrap());
     }
     TileMap(data, width)
 }
 
+#[derive(Clone)]
 pub struct Spritesheet {
     pub texture: Texture2D,
     pub sprite_size: f32,
@@ -176,141 +252,314 @@ impl Spritesheet {
             sprite_size,
         }
     }
-    /// Same as `draw_tile`, except centered
-    pub fn draw_sprite(
-        &self,
-        screen_x: f32,
-        screen_y: f32,
-        tile_x: f32,
-        tile_y: f32,
-        params: Option<&DrawTextureParams>,
-    ) {
-        self.draw_tile(
-            screen_x - self.sprite_size / 2.0,
-            screen_y - self.sprite_size / 2.0,
-            tile_x,
-            tile_y,
-            params,
-        );
-    }
-    /// Draws a single tile from the spritesheet
-    pub fn draw_tile(
-        &self,
-        screen_x: f32,
-        screen_y: f32,
-        tile_x: f32,
-        tile_y: f32,
-        params: Option<&DrawTextureParams>,
-    ) {
-        let mut p = params.cloned().unwrap_or(DrawTextureParams::default());
-        p.dest_size = p
-            .dest_size
-            .or(Some(Vec2::new(self.sprite_size, self.sprite_size)));
-        p.source = p.source.or(Some(Rect {
-            x: tile_x * self.sprite_size,
-            y: tile_y * self.sprite_size,
-            w: self.sprite_size,
-            h: self.sprite_size,
-        }));
-        draw_texture_ex(&self.texture, screen_x, screen_y, WHITE, p);
+    /// Part of the texture holding a tile, by its map id, where 0 is empty and 1 is the first tile of the sheet
+    pub fn tile_source(&self, tile: u8) -> Rect {
+        let spritesheet_width = (self.texture.width() / self.sprite_size) as u8;
+        let tile = tile - 1;
+        Rect::new(
+            (tile % spritesheet_width) as f32 * self.sprite_size,
+            (tile / spritesheet_width) as f32 * self.sprite_size,
+            self.sprite_size,
+            self.sprite_size,
+        )
     }
 }
 pub struct AnimationsGroup {
-    #[expect(dead_code)]
-    pub file: AsepriteFile,
     pub animations: Vec<Animation>,
     pub tag_names: HashMap<String, usize>,
+    /// Every frame of the file in order, including ones no tag covers
+    pub frames: Vec<Frame>,
+    /// Point of the sprite that sits on its owner's `TILE_ANCHOR`
+    pub pivot: Vec2,
 }
 impl AnimationsGroup {
     pub fn get_by_name(&self, name: &str) -> &Animation {
         &self.animations[*self.tag_names.get(name).unwrap()]
     }
-    pub fn from_file(bytes: &[u8]) -> Self {
+    pub fn from_file(bytes: &[u8], atlas: &mut AtlasBuilder) -> Self {
         let ase = AsepriteFile::read(bytes).unwrap();
-        let mut frames = Vec::new();
-        for index in 0..ase.num_frames() {
-            let frame = ase.frame(index);
-            let img = frame.image();
-            let new = Image {
-                width: img.width() as u16,
-                height: img.height() as u16,
-                bytes: img.as_bytes().to_vec(),
-            };
-            let duration = frame.duration();
-            let texture = Texture2D::from_image(&new);
-            texture.set_filter(FilterMode::Nearest);
-            frames.push((texture, duration));
-        }
-        let mut tag_frames = Vec::new();
-        let mut offset = 0;
-
-        let mut tag_names = HashMap::new();
-
-        for i in 0..ase.num_tags() {
-            let tag = ase.get_tag(i).unwrap();
-            tag_names.insert(tag.name().to_string(), i as usize);
-            let (start, end) = (tag.from_frame() as usize, tag.to_frame() as usize);
-            let mut total_length = 0;
-            let included_frames: Vec<(Texture2D, u32)> = frames
-                .extract_if((start - offset)..(end - offset + 1), |_| true)
-                .collect();
-            for f in included_frames.iter() {
-                total_length += f.1;
-            }
-            offset += end.abs_diff(start) + 1;
-            tag_frames.push(Animation {
-                frames: included_frames,
-                total_length,
-            });
-        }
+        let tags: Vec<TagRange> = (0..ase.num_tags())
+            .map(|i| {
+                let tag = ase.get_tag(i).unwrap();
+                let (mode, repeats) = playback_mode(tag);
+                TagRange {
+                    name: tag.name().to_string(),
+                    from: tag.from_frame() as usize,
+                    to: tag.to_frame() as usize,
+                    direction: tag.animation_direction(),
+                    mode,
+                    repeats,
+                }
+            })
+            .collect();
+        Self::new(load_frames(&ase, atlas, true), &tags, read_pivot(&ase))
+    }
+    /// An animation for every tag, out of every frame of a file
+    pub fn new(frames: Vec<Frame>, tags: &[TagRange], pivot: Vec2) -> Self {
+        // tags may overlap, leave gaps or come in any order, so every tag copies its
+        // own range out of the full frame list
+        let animations = tags
+            .iter()
+            .map(|tag| {
+                Animation::new(
+                    frames[tag.from..=tag.to].to_vec(),
+                    tag.direction,
+                    tag.mode,
+                    tag.repeats,
+                )
+            })
+            .collect();
         Self {
-            file: ase,
-            animations: tag_frames,
-            tag_names,
+            animations,
+            tag_names: tags
+                .iter()
+                .enumerate()
+                .map(|(index, tag)| (tag.name.clone(), index))
+                .collect(),
+            frames,
+            pivot,
         }
     }
 }
-pub struct Animation {
-    frames: Vec<(Texture2D, u32)>,
-    pub total_length: u32,
+
+/// The frames a tag covers, both ends included, and how it plays them
+pub struct TagRange {
+    pub name: String,
+    pub from: usize,
+    pub to: usize,
+    pub direction: AnimationDirection,
+    pub mode: PlaybackMode,
+    pub repeats: u32,
 }
-impl Animation {
-    pub fn from_file(bytes: &[u8]) -> Self {
-        let ase = AsepriteFile::read(bytes).unwrap();
-        let mut frames = Vec::new();
-        let mut total_length = 0;
-        for index in 0..ase.num_frames() {
+
+/// Reads the pivot from the file's `pivot` slice. Slices without a pivot point use their
+/// bottom centre, and files without the slice line their top left corner up with the tile.
+fn read_pivot(ase: &AsepriteFile) -> Vec2 {
+    let Some(key) = ase
+        .slices()
+        .iter()
+        .find(|f| f.name == "pivot")
+        .and_then(|f| f.keys.first())
+    else {
+        return TILE_ANCHOR;
+    };
+    let (x, y) = key.origin;
+    let (pivot_x, pivot_y) = key
+        .pivot
+        .unwrap_or((key.size.0 as i32 / 2, key.size.1 as i32));
+    vec2((x + pivot_x) as f32, (y + pivot_y) as f32)
+}
+
+/// A single frame of an animation, stored as a region of the atlas
+#[derive(Clone)]
+pub struct Frame {
+    pub region: usize,
+    pub size: Vec2,
+    /// Region of a 1 pixel outline around the frame's opaque pixels, for highlighting.
+    /// It's a pixel bigger than the frame on every side. Only sprites get one.
+    pub outline: Option<usize>,
+    pub duration: u32,
+    /// Events fired when playback reaches this frame, like `sound:knock` or `event:cup_poured`
+    pub events: Vec<String>,
+}
+
+/// Reads every frame of an aseprite file into the atlas, in file order, with outlines if `outlines` is set
+fn load_frames(ase: &AsepriteFile, atlas: &mut AtlasBuilder, outlines: bool) -> Vec<Frame> {
+    (0..ase.num_frames())
+        .map(|index| {
             let frame = ase.frame(index);
             let img = frame.image();
-            let new = Image {
-                width: img.width() as u16,
-                height: img.height() as u16,
-                bytes: img.as_bytes().to_vec(),
-            };
-            let duration = frame.duration();
-            total_length += duration;
-            let texture = Texture2D::from_image(&new);
-            texture.set_filter(FilterMode::Nearest);
-            frames.push((texture, duration));
+            Frame {
+                size: vec2(img.width() as f32, img.height() as f32),
+                outline: outlines.then(|| atlas.add(outline_mask(&img))),
+                region: atlas.add(img),
+                duration: frame.duration(),
+                events: read_events(ase, index),
+            }
+        })
+        .collect()
+}
+
+/// White wherever a see-through pixel touches an opaque one, with a pixel of room around the image for edges
+pub fn outline_mask(image: &RgbaImage) -> RgbaImage {
+    let (w, h) = image.dimensions();
+    let opaque = |x: i64, y: i64| {
+        x >= 0
+            && y >= 0
+            && x < w as i64
+            && y < h as i64
+            && image.get_pixel(x as u32, y as u32)[3] > 0
+    };
+    RgbaImage::from_fn(w + 2, h + 2, |x, y| {
+        // position in the original image
+        let (x, y) = (x as i64 - 1, y as i64 - 1);
+        let touching = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+            .iter()
+            .any(|(dx, dy)| opaque(x + dx, y + dy));
+        if !opaque(x, y) && touching {
+            image::Rgba([255, 255, 255, 255])
+        } else {
+            image::Rgba([0, 0, 0, 0])
         }
+    })
+}
+
+/// Reads events from the text user data of a frame's cels, one per line or comma
+fn read_events(ase: &AsepriteFile, frame: u32) -> Vec<String> {
+    (0..ase.num_layers())
+        .filter_map(|layer| ase.cel(frame, layer).user_data()?.text.clone())
+        .flat_map(|text| {
+            text.split([',', '\n'])
+                .map(|f| f.trim().to_string())
+                .filter(|f| !f.is_empty())
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// What an animation does once it has played through
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PlaybackMode {
+    Loop,
+    /// Goes back to the first frame
+    Once,
+    /// Stays on the last frame
+    HoldLast,
+}
+
+/// Reads the playback mode from a tag. Text user data of `loop`, `once` or `hold` picks the
+/// mode, otherwise tags with a repeat count hold their last frame and the rest loop.
+fn playback_mode(tag: &asefile::Tag) -> (PlaybackMode, u32) {
+    let repeats = tag.repeat().map(|f| f.get()).unwrap_or(1);
+    let mode = match tag.user_data().and_then(|f| f.text.as_deref()) {
+        Some("loop") => PlaybackMode::Loop,
+        Some("once") => PlaybackMode::Once,
+        Some("hold") => PlaybackMode::HoldLast,
+        _ if tag.repeat().is_some() => PlaybackMode::HoldLast,
+        _ => PlaybackMode::Loop,
+    };
+    (mode, repeats)
+}
+
+pub struct Animation {
+    frames: Vec<Frame>,
+    /// Indices into `frames` in the order they are shown, over one cycle of a loop
+    /// or every repeat of an animation that stops
+    sequence: Vec<usize>,
+    /// Length of `sequence`, in milliseconds
+    pub cycle_length: u32,
+    pub mode: PlaybackMode,
+}
+impl Animation {
+    /// `repeats` is how many passes play before a non looping animation stops
+    pub fn new(
+        frames: Vec<Frame>,
+        direction: AnimationDirection,
+        mode: PlaybackMode,
+        repeats: u32,
+    ) -> Self {
+        let count = frames.len();
+        let sequence = match (mode, direction) {
+            // there and back again, leaving off the first frame so it isn't shown twice in a row
+            // when the loop comes back round
+            (PlaybackMode::Loop, AnimationDirection::PingPong) => {
+                let mut sequence = play_order(count, direction, 2);
+                if count > 1 {
+                    sequence.pop();
+                }
+                sequence
+            }
+            (PlaybackMode::Loop, _) => play_order(count, direction, 1),
+            _ => play_order(count, direction, repeats.max(1)),
+        };
         Self {
+            cycle_length: sequence.iter().map(|f| frames[*f].duration).sum(),
             frames,
-            total_length,
+            sequence,
+            mode,
+        }
+    }
+    pub fn from_file(bytes: &[u8], atlas: &mut AtlasBuilder) -> Self {
+        let ase = AsepriteFile::read(bytes).unwrap();
+        Self::new(
+            load_frames(&ase, atlas, false),
+            AnimationDirection::Forward,
+            PlaybackMode::Loop,
+            1,
+        )
+    }
+    /// Time it takes for the animation to finish, in milliseconds.
+    /// Looping animations count as finished after their first cycle.
+    pub fn length(&self) -> u32 {
+        self.cycle_length
+    }
+    pub fn is_finished(&self, time: u32) -> bool {
+        time >= self.length()
+    }
+    /// Events of every frame that starts playing within `from..to` milliseconds
+    pub fn events_between(&self, from: u32, to: u32) -> Vec<&str> {
+        let mut events = Vec::new();
+        let end = match self.mode {
+            PlaybackMode::Loop => to,
+            _ => to.min(self.length()),
+        };
+        if self.cycle_length == 0 {
+            return events;
+        }
+        let mut cycle_start = from / self.cycle_length * self.cycle_length;
+        while cycle_start < end {
+            let mut start = cycle_start;
+            for index in self.sequence.iter() {
+                if start >= end {
+                    break;
+                }
+                if start >= from {
+                    events.extend(self.frames[*index].events.iter().map(|f| f.as_str()));
+                }
+                start += self.frames[*index].duration;
+            }
+            cycle_start += self.cycle_length;
         }
+        events
     }
-    pub fn get_at_time(&self, mut time: u32) -> &Texture2D {
-        time %= self.total_length;
-        for (texture, length) in self.frames.iter() {
-            if time >= *length {
-                time -= length;
+    pub fn get_at_time(&self, time: u32) -> &Frame {
+        let index = match self.mode {
+            PlaybackMode::Once if self.is_finished(time) => self.sequence[0],
+            PlaybackMode::HoldLast if self.is_finished(time) => *self.sequence.last().unwrap(),
+            _ => self.index_in_cycle(time % self.cycle_length),
+        };
+        &self.frames[index]
+    }
+    fn index_in_cycle(&self, mut time: u32) -> usize {
+        for index in self.sequence.iter() {
+            let duration = self.frames[*index].duration;
+            if time >= duration {
+                time -= duration;
             } else {
-                return texture;
+                return *index;
             }
         }
         panic!()
     }
 }
 
+/// Order `count` frames play in over `passes` passes, like Aseprite counts a tag's repeats.
+/// Ping-pong turns around on every pass, without showing the frame it turns on twice.
+fn play_order(count: usize, direction: AnimationDirection, passes: u32) -> Vec<usize> {
+    let mut sequence = Vec::new();
+    for pass in 0..passes {
+        match direction {
+            AnimationDirection::Forward => sequence.extend(0..count),
+            AnimationDirection::Reverse => sequence.extend((0..count).rev()),
+            AnimationDirection::PingPong if pass % 2 == 0 => {
+                sequence.extend((0..count).skip((pass > 0) as usize))
+            }
+            AnimationDirection::PingPong => sequence.extend((0..count).rev().skip(1)),
+        }
+    }
+    sequence
+}
+
 fn load_ase_texture(bytes: &[u8], layer: Option<u32>) -> Texture2D {
     let img = AsepriteFile::read(bytes).unwrap();
     let img = if let Some(layer) = layer {
@@ -327,3 +576,172 @@ fn load_ase_texture(bytes: &[u8], layer: Option<u32>) -> Texture2D {
     texture.set_filter(FilterMode::Nearest);
     texture
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `count` frames of 100ms each, told apart by their region
+    fn frames(count: usize) -> Vec<Frame> {
+        (0..count)
+            .map(|region| Frame {
+                region,
+                size: Vec2::ZERO,
+                outline: None,
+                duration: 100,
+                events: Vec::new(),
+            })
+            .collect()
+    }
+
+    /// Region shown at the start of every 100ms from 0 to `until`
+    fn shown(animation: &Animation, until: u32) -> Vec<usize> {
+        (0..=until / 100)
+            .map(|f| animation.get_at_time(f * 100).region)
+            .collect()
+    }
+
+    #[test]
+    fn loops_go_round_forever() {
+        use AnimationDirection::*;
+        for (direction, cycle) in [
+            (Forward, vec![0, 1, 2]),
+            (Reverse, vec![2, 1, 0]),
+            (PingPong, vec![0, 1, 2, 1]),
+        ] {
+            // repeats only count for animations that stop
+            let animation = Animation::new(frames(3), direction, PlaybackMode::Loop, 3);
+            let length = cycle.len() as u32 * 100;
+            assert_eq!(animation.length(), length, "{direction:?}");
+            assert!(!animation.is_finished(length - 1));
+            assert!(animation.is_finished(length));
+            let twice: Vec<usize> = cycle.iter().chain(&cycle).copied().collect();
+            assert_eq!(shown(&animation, length * 2 - 100), twice, "{direction:?}");
+        }
+    }
+
+    #[test]
+    fn once_goes_back_to_the_start() {
+        use AnimationDirection::*;
+        for (direction, repeats, played) in [
+            (Forward, 2, vec![0, 1, 2, 0, 1, 2]),
+            (Reverse, 2, vec![2, 1, 0, 2, 1, 0]),
+            (PingPong, 1, vec![0, 1, 2]),
+            (PingPong, 2, vec![0, 1, 2, 1, 0]),
+        ] {
+            let animation = Animation::new(frames(3), direction, PlaybackMode::Once, repeats);
+            let length = played.len() as u32 * 100;
+            assert_eq!(animation.length(), length, "{direction:?} {repeats}");
+            assert!(!animation.is_finished(length - 1));
+            assert!(animation.is_finished(length));
+            let mut expected = played.clone();
+            expected.extend([played[0], played[0]]);
+            assert_eq!(shown(&animation, length + 100), expected, "{direction:?}");
+        }
+    }
+
+    #[test]
+    fn hold_last_stays_where_the_last_pass_ends() {
+        use AnimationDirection::*;
+        for (direction, repeats, played) in [
+            (Forward, 2, vec![0, 1, 2, 0, 1, 2]),
+            (Reverse, 2, vec![2, 1, 0, 2, 1, 0]),
+            (PingPong, 1, vec![0, 1, 2]),
+            (PingPong, 2, vec![0, 1, 2, 1, 0]),
+            (PingPong, 3, vec![0, 1, 2, 1, 0, 1, 2]),
+        ] {
+            let animation = Animation::new(frames(3), direction, PlaybackMode::HoldLast, repeats);
+            let length = played.len() as u32 * 100;
+            assert_eq!(animation.length(), length, "{direction:?} {repeats}");
+            assert!(animation.is_finished(length));
+            let last = *played.last().unwrap();
+            let mut expected = played.clone();
+            expected.extend([last, last]);
+            assert_eq!(
+                shown(&animation, length + 100),
+                expected,
+                "{direction:?} {repeats}"
+            );
+        }
+    }
+
+    fn tag(name: &str, from: usize, to: usize) -> TagRange {
+        TagRange {
+            name: name.to_string(),
+            from,
+            to,
+            direction: AnimationDirection::Forward,
+            mode: PlaybackMode::Loop,
+            repeats: 1,
+        }
+    }
+
+    #[test]
+    fn tags_take_their_own_frames() {
+        // out of order, with "lid" overlapping "pour" and frame 3 left out of every tag
+        let tags = [tag("pour", 4, 6), tag("idle", 0, 2), tag("lid", 5, 6)];
+        let group = AnimationsGroup::new(frames(7), &tags, Vec2::ZERO);
+        assert_eq!(shown(group.get_by_name("idle"), 200), [0, 1, 2]);
+        assert_eq!(shown(group.get_by_name("pour"), 200), [4, 5, 6]);
+        assert_eq!(shown(group.get_by_name("lid"), 100), [5, 6]);
+        let regions: Vec<usize> = group.frames.iter().map(|f| f.region).collect();
+        assert_eq!(regions, [0, 1, 2, 3, 4, 5, 6]);
+    }
+
+    /// Three 100ms frames firing "a", "b" and "c"
+    fn with_events(mode: PlaybackMode) -> Animation {
+        let mut frames = frames(3);
+        for (frame, event) in frames.iter_mut().zip(["a", "b", "c"]) {
+            frame.events.push(event.to_string());
+        }
+        Animation::new(frames, AnimationDirection::Forward, mode, 1)
+    }
+
+    #[test]
+    fn events_fire_as_frames_start() {
+        let animation = with_events(PlaybackMode::Loop);
+        assert_eq!(animation.events_between(0, 100), ["a"]);
+        // a frame starting right at `from` fires, one starting right at `to` waits for the next call
+        assert_eq!(animation.events_between(100, 200), ["b"]);
+        assert_eq!(animation.events_between(50, 250), ["b", "c"]);
+        assert!(animation.events_between(100, 100).is_empty());
+        assert!(animation.events_between(110, 190).is_empty());
+    }
+
+    #[test]
+    fn loop_events_wrap_around() {
+        let animation = with_events(PlaybackMode::Loop);
+        assert_eq!(animation.events_between(250, 450), ["a", "b"]);
+        assert_eq!(
+            animation.events_between(50, 650),
+            ["b", "c", "a", "b", "c", "a"]
+        );
+        assert_eq!(animation.events_between(900, 1000), ["a"]);
+    }
+
+    #[test]
+    fn events_stop_when_the_animation_does() {
+        for mode in [PlaybackMode::Once, PlaybackMode::HoldLast] {
+            let animation = with_events(mode);
+            assert_eq!(
+                animation.events_between(0, 1000),
+                ["a", "b", "c"],
+                "{mode:?}"
+            );
+            assert_eq!(animation.events_between(200, 1000), ["c"]);
+            assert!(animation.events_between(300, 1000).is_empty());
+        }
+    }
+
+    #[test]
+    fn single_frame_ping_pong() {
+        let animation = Animation::new(
+            frames(1),
+            AnimationDirection::PingPong,
+            PlaybackMode::Loop,
+            1,
+        );
+        assert_eq!(animation.length(), 100);
+        assert_eq!(shown(&animation, 300), [0, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,216 @@
+use std::cmp::Reverse;
+
+use image::{EncodableLayout, RgbaImage};
+use macroquad::prelude::*;
+
+/// Width of an atlas page. Pages are cropped to the height they actually use.
+pub const PAGE_SIZE: u32 = 2048;
+/// Empty pixels kept between packed images, so neighbours never bleed into each other
+const PADDING: u32 = 1;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Placement {
+    pub page: usize,
+    pub x: u32,
+    pub y: u32,
+}
+
+/// Shelf packs rectangles of the given sizes into square pages of `page_size`.
+///
+/// Returns where each size was placed, in the same order as `sizes`.
+pub fn pack(sizes: &[(u32, u32)], page_size: u32) -> Vec<Placement> {
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    // tallest first, so every shelf wastes as little height as possible
+    order.sort_by_key(|i| (Reverse(sizes[*i].1), Reverse(sizes[*i].0)));
+
+    let mut placements = vec![
+        Placement {
+            page: 0,
+            x: 0,
+            y: 0
+        };
+        sizes.len()
+    ];
+    let (mut page, mut x, mut y, mut shelf_height) = (0, 0, 0, 0);
+    for index in order {
+        let (w, h) = sizes[index];
+        assert!(
+            w <= page_size && h <= page_size,
+            "a {w}x{h} image doesn't fit on a {page_size}x{page_size} atlas page"
+        );
+        if x + w > page_size {
+            x = 0;
+            y += shelf_height;
+            shelf_height = 0;
+        }
+        if y + h > page_size {
+            page += 1;
+            x = 0;
+            y = 0;
+            shelf_height = 0;
+        }
+        placements[index] = Placement { page, x, y };
+        x += w + PADDING;
+        shelf_height = shelf_height.max(h + PADDING);
+    }
+    placements
+}
+
+/// Collects images while assets load, to be packed into an `Atlas` once everything is read
+#[derive(Default)]
+pub struct AtlasBuilder {
+    images: Vec<RgbaImage>,
+}
+impl AtlasBuilder {
+    /// Adds an image and returns the region index it will have in the built atlas
+    pub fn add(&mut self, image: RgbaImage) -> usize {
+        self.images.push(image);
+        self.images.len() - 1
+    }
+    /// Packs every image added so far into pages
+    pub fn build(self) -> Atlas {
+        let sizes: Vec<(u32, u32)> = self.images.iter().map(|f| f.dimensions()).collect();
+        let placements = pack(&sizes, PAGE_SIZE);
+
+        let page_count = placements.iter().map(|f| f.page + 1).max().unwrap_or(0);
+        let mut page_heights = vec![0; page_count];
+        for (placement, (_, h)) in placements.iter().zip(sizes.iter()) {
+            page_heights[placement.page] = page_heights[placement.page].max(placement.y + h);
+        }
+        let mut pages: Vec<RgbaImage> = page_heights
+            .iter()
+            .map(|h| RgbaImage::new(PAGE_SIZE, *h))
+            .collect();
+        let mut regions = Vec::with_capacity(self.images.len());
+        for (image, placement) in self.images.iter().zip(placements.iter()) {
+            image::imageops::replace(
+                &mut pages[placement.page],
+                image,
+                placement.x as i64,
+                placement.y as i64,
+            );
+            regions.push((
+                placement.page,
+                Rect::new(
+                    placement.x as f32,
+                    placement.y as f32,
+                    image.width() as f32,
+                    image.height() as f32,
+                ),
+            ));
+        }
+        Atlas { pages, regions }
+    }
+}
+
+/// All animation frames, packed into as few pages as possible
+pub struct Atlas {
+    pub pages: Vec<RgbaImage>,
+    /// Page index and source rect of every image added to the builder
+    pub regions: Vec<(usize, Rect)>,
+}
+impl Atlas {
+    /// Turns every page into a texture, in the same order
+    pub fn upload(&self) -> Vec<Texture2D> {
+        self.pages
+            .iter()
+            .map(|page| {
+                let texture = Texture2D::from_image(&Image {
+                    width: page.width() as u16,
+                    height: page.height() as u16,
+                    bytes: page.as_bytes().to_vec(),
+                });
+                texture.set_filter(FilterMode::Nearest);
+                texture
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Whether two placed rectangles, grown by `gap` on the right and bottom, overlap
+    fn overlaps(a: (Placement, (u32, u32)), b: (Placement, (u32, u32)), gap: u32) -> bool {
+        let ((a, (aw, ah)), (b, (bw, bh))) = (a, b);
+        a.page == b.page
+            && a.x < b.x + bw + gap
+            && b.x < a.x + aw + gap
+            && a.y < b.y + bh + gap
+            && b.y < a.y + ah + gap
+    }
+
+    #[test]
+    fn nothing_overlaps() {
+        let sizes: Vec<(u32, u32)> = (0..60).map(|i| (5 + i % 13, 3 + i % 7)).collect();
+        let placements = pack(&sizes, 64);
+        for (i, a) in placements.iter().zip(&sizes).enumerate() {
+            let (placement, (w, h)) = a;
+            assert!(placement.x + w <= 64 && placement.y + h <= 64);
+            for b in placements.iter().zip(&sizes).skip(i + 1) {
+                assert!(!overlaps((*a.0, *a.1), (*b.0, *b.1), 0));
+            }
+        }
+    }
+
+    #[test]
+    fn padding_between_regions() {
+        let sizes: Vec<(u32, u32)> = (0..40).map(|i| (4 + i % 5, 4 + i % 3)).collect();
+        let placements = pack(&sizes, 32);
+        for (i, a) in placements.iter().zip(&sizes).enumerate() {
+            for b in placements.iter().zip(&sizes).skip(i + 1) {
+                assert!(!overlaps((*a.0, *a.1), (*b.0, *b.1), PADDING));
+            }
+        }
+    }
+
+    #[test]
+    fn wraps_onto_a_new_shelf() {
+        let placements = pack(&[(10, 10); 3], 25);
+        assert_eq!(
+            placements[0],
+            Placement {
+                page: 0,
+                x: 0,
+                y: 0
+            }
+        );
+        assert_eq!(
+            placements[1],
+            Placement {
+                page: 0,
+                x: 11,
+                y: 0
+            }
+        );
+        // a third doesn't fit on the first shelf
+        assert_eq!(
+            placements[2],
+            Placement {
+                page: 0,
+                x: 0,
+                y: 11
+            }
+        );
+    }
+
+    #[test]
+    fn full_page_starts_another() {
+        let placements = pack(&[(16, 16); 2], 20);
+        assert_eq!(
+            placements[1],
+            Placement {
+                page: 1,
+                x: 0,
+                y: 0
+            }
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "doesn't fit")]
+    fn too_big_for_a_page() {
+        pack(&[(8, 8), (40, 8)], 32);
+    }
+}
@@ -0,0 +1,219 @@
+#[cfg(feature = "audio")]
+use std::collections::HashMap;
+
+#[cfg(feature = "audio")]
+use macroquad::audio::{
+    PlaySoundParams, Sound, load_sound_from_bytes, play_sound, set_sound_volume,
+};
+
+use macroquad::logging::warn;
+
+use crate::{
+    assets::Map,
+    manifest::{DEFAULT_FOOTSTEP, FOOTSTEPS, LOOPS, SOUNDS},
+    soundscape::Soundscape,
+};
+#[cfg(feature = "audio")]
+use crate::{manifest::VOICES, synth::encode_wav, voice::BLIP_VARIANTS};
+
+/// Decodes and mixes sounds, however the platform does it
+pub trait AudioBackend {
+    /// Starts a sound from `SOUNDS` by name, at a volume from 0 to 1
+    fn play(&self, sound: &str, volume: f32);
+    /// Sets the volume of a sound from `LOOPS`, which are always playing
+    fn set_loop_volume(&self, sound: &str, volume: f32);
+    /// Starts one of the blips of a speaker from `VOICES`
+    fn play_blip(&self, voice: &str, variant: usize, volume: f32);
+}
+
+/// Plays nothing, for builds without audio and for running headless
+#[cfg_attr(feature = "audio", allow(dead_code))]
+pub struct NullAudio;
+impl AudioBackend for NullAudio {
+    fn play(&self, _sound: &str, _volume: f32) {}
+    fn set_loop_volume(&self, _sound: &str, _volume: f32) {}
+    fn play_blip(&self, _voice: &str, _variant: usize, _volume: f32) {}
+}
+
+/// Plays sounds through macroquad
+#[cfg(feature = "audio")]
+pub struct MacroquadAudio {
+    sounds: HashMap<&'static str, Sound>,
+    loops: HashMap<&'static str, Sound>,
+    blips: HashMap<&'static str, Vec<Sound>>,
+}
+#[cfg(feature = "audio")]
+impl MacroquadAudio {
+    /// Synthesizes every sound and hands it to macroquad to decode.
+    /// Loops start straight away, silent until the soundscape fades them in.
+    pub async fn load() -> Self {
+        let mut sounds = HashMap::new();
+        for (index, (name, effect)) in SOUNDS.iter().enumerate() {
+            sounds.insert(
+                *name,
+                load_samples(name, &effect.render(index as u64)).await,
+            );
+        }
+        let mut loops = HashMap::new();
+        for (index, (name, sound)) in LOOPS.iter().enumerate() {
+            let sound = load_samples(name, &sound.render(index as u64)).await;
+            play_sound(
+                &sound,
+                PlaySoundParams {
+                    looped: true,
+                    volume: 0.0,
+                },
+            );
+            loops.insert(*name, sound);
+        }
+        let mut blips = HashMap::new();
+        for (name, voice) in VOICES {
+            let mut variants = Vec::new();
+            for variant in 0..BLIP_VARIANTS {
+                variants.push(load_samples(name, &voice.blip(variant)).await);
+            }
+            blips.insert(*name, variants);
+        }
+        Self {
+            sounds,
+            loops,
+            blips,
+        }
+    }
+}
+#[cfg(feature = "audio")]
+impl MacroquadAudio {
+    fn play_once(&self, sound: &Sound, volume: f32) {
+        play_sound(
+            sound,
+            PlaySoundParams {
+                looped: false,
+                volume,
+            },
+        );
+    }
+}
+#[cfg(feature = "audio")]
+impl AudioBackend for MacroquadAudio {
+    fn play(&self, sound: &str, volume: f32) {
+        self.play_once(&self.sounds[sound], volume);
+    }
+    fn set_loop_volume(&self, sound: &str, volume: f32) {
+        set_sound_volume(&self.loops[sound], volume);
+    }
+    fn play_blip(&self, voice: &str, variant: usize, volume: f32) {
+        self.play_once(&self.blips[voice][variant], volume);
+    }
+}
+#[cfg(feature = "audio")]
+async fn load_samples(name: &str, samples: &[f32]) -> Sound {
+    load_sound_from_bytes(&encode_wav(samples))
+        .await
+        .unwrap_or_else(|err| panic!("couldn't load sound {name:?}: {err}"))
+}
+
+/// Collects the sounds started during a frame and plays them at the end of it,
+/// keeping them around until the next frame to check what played
+pub struct Audio<'a> {
+    backend: &'a dyn AudioBackend,
+    /// Sounds started this frame, in order
+    pub triggered: Vec<&'a str>,
+    /// Voice blips started this frame, as the speaker from `VOICES` and the blip's variant
+    pub blips: Vec<(&'static str, usize)>,
+    /// Volume of every sound effect, from 0 to 1
+    pub volume: f32,
+    /// Volume of music and ambience, from 0 to 1
+    pub music_volume: f32,
+}
+impl<'a> Audio<'a> {
+    pub fn new(backend: &'a dyn AudioBackend) -> Self {
+        Self {
+            backend,
+            triggered: Vec::new(),
+            blips: Vec::new(),
+            volume: 1.0,
+            music_volume: 1.0,
+        }
+    }
+    /// Starts a sound from `SOUNDS`. Names come from sprite data too, so a sound that
+    /// doesn't exist is logged and skipped rather than taking the game down.
+    pub fn play(&mut self, sound: &'a str) {
+        if !SOUNDS.iter().any(|(name, _)| *name == sound) {
+            warn!("no sound named {sound:?}");
+            return;
+        }
+        self.triggered.push(sound);
+    }
+    pub fn play_blip(&mut self, voice: &'static str, variant: usize) {
+        self.blips.push((voice, variant));
+    }
+    /// Plays the sounds of any `sound:<name>` events
+    pub fn play_events(&mut self, events: &[&'a str]) {
+        for event in events {
+            if let Some(sound) = event.strip_prefix("sound:") {
+                self.play(sound);
+            }
+        }
+    }
+    /// Forgets the last frame's sounds
+    pub fn begin_frame(&mut self) {
+        self.triggered.clear();
+        self.blips.clear();
+    }
+    /// Hands this frame's sounds, blips and the soundscape's volumes to the backend
+    pub fn finish_frame(&self, soundscape: &Soundscape) {
+        for sound in &self.triggered {
+            self.backend.play(sound, self.volume);
+        }
+        for (voice, variant) in &self.blips {
+            self.backend.play_blip(voice, *variant, self.volume);
+        }
+        for (sound, volume) in soundscape.sound_volumes() {
+            self.backend
+                .set_loop_volume(sound, volume * self.music_volume);
+        }
+    }
+    /// Mutes every loop, for when nothing is updating the soundscape anymore
+    pub fn silence_loops(&self) {
+        for (sound, _) in LOOPS {
+            self.backend.set_loop_volume(sound, 0.0);
+        }
+    }
+}
+
+/// Footstep sound of walking onto the tile at (x, y)
+pub fn footstep_sound(map: &Map, x: usize, y: usize) -> &'static str {
+    for layer in [&map.floor_decorations, &map.floor] {
+        let tile = layer.0[x + y * layer.1];
+        if let Some((_, sound)) = FOOTSTEPS.iter().find(|(tiles, _)| tiles.contains(&tile)) {
+            return sound;
+        }
+    }
+    DEFAULT_FOOTSTEP
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{settings::TextSpeed, voice::Typewriter};
+
+    #[test]
+    fn plays_this_ticks_sounds() {
+        let backend = NullAudio;
+        let mut audio = Audio::new(&backend);
+        audio.begin_frame();
+        audio.play_events(&["event:door_open", "sound:knock", "sound:no_such_sound"]);
+        audio.play("step_wood");
+        assert_eq!(audio.triggered, ["knock", "step_wood"]);
+        assert!(audio.blips.is_empty());
+
+        // the next tick starts from nothing, and dialogue being revealed blips
+        audio.begin_frame();
+        let mut typewriter = Typewriter::default();
+        typewriter.reveal("Hello", "Ferret");
+        typewriter.update(1.0, TextSpeed::Normal, &mut audio);
+        assert!(audio.triggered.is_empty());
+        assert!(!audio.blips.is_empty());
+        assert!(audio.blips.iter().all(|(voice, _)| *voice == "Ferret"));
+    }
+}
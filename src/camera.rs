@@ -0,0 +1,206 @@
+use macroquad::prelude::*;
+
+use crate::utils::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+/// How far the player can wander from the middle of the screen before the camera follows,
+/// in pixels either way
+const DEAD_ZONE: Vec2 = Vec2::new(24.0, 16.0);
+/// How quickly the camera catches up, as the fraction of the distance left covered per second.
+/// Panning to something other than the player is slower, to give cutscenes time to breathe.
+const FOLLOW_SPEED: f32 = 8.0;
+const PAN_SPEED: f32 = 3.0;
+const ZOOM_SPEED: f32 = 3.0;
+/// How much shake wears off every second, in pixels
+const SHAKE_DECAY: f32 = 12.0;
+
+/// What the camera is looking at
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CameraTarget {
+    Player,
+    /// Index into the game's characters
+    Character(usize),
+    /// World position
+    Point(Vec2),
+}
+
+pub struct Camera {
+    /// World position in the middle of the screen, before shake
+    pub pos: Vec2,
+    pub target: CameraTarget,
+    pub zoom: f32,
+    pub target_zoom: f32,
+    /// Strength of the current shake, in pixels
+    pub shake: f32,
+    time: f32,
+}
+impl Camera {
+    pub fn new(pos: Vec2) -> Self {
+        Self {
+            pos,
+            target: CameraTarget::Player,
+            zoom: 1.0,
+            target_zoom: 1.0,
+            shake: 0.0,
+            time: 0.0,
+        }
+    }
+    pub fn following_player(&self) -> bool {
+        self.target == CameraTarget::Player
+    }
+    /// Goes back to following the player at normal zoom
+    pub fn reset(&mut self) {
+        self.target = CameraTarget::Player;
+        self.target_zoom = 1.0;
+    }
+    /// Starts a shake, unless a stronger one is already going
+    pub fn add_shake(&mut self, amount: f32) {
+        self.shake = self.shake.max(amount);
+    }
+    /// Moves towards `goal`, the world position of the current target, keeping the view inside `bounds`
+    pub fn update(&mut self, goal: Vec2, bounds: Rect, delta_time: f32) {
+        self.time += delta_time;
+        self.shake = (self.shake - SHAKE_DECAY * delta_time).max(0.0);
+        self.zoom += (self.target_zoom - self.zoom) * smoothing(ZOOM_SPEED, delta_time);
+
+        let desired = if self.following_player() {
+            // only move once the player leaves the dead zone, and only as far as needed
+            let offset = goal - self.pos;
+            self.pos + offset - offset.clamp(-DEAD_ZONE, DEAD_ZONE)
+        } else {
+            goal
+        };
+        let speed = if self.following_player() {
+            FOLLOW_SPEED
+        } else {
+            PAN_SPEED
+        };
+        self.pos += (desired - self.pos) * smoothing(speed, delta_time);
+        self.pos = clamp_to_bounds(self.pos, self.half_view(), bounds);
+    }
+    /// Half the size of the world area on screen at the current zoom
+    fn half_view(&self) -> Vec2 {
+        vec2(SCREEN_WIDTH, SCREEN_HEIGHT) / 2.0 / self.zoom
+    }
+    /// World position in the middle of the screen this frame, shaken and snapped to whole pixels
+    pub fn view_pos(&self) -> Vec2 {
+        let shake = vec2(
+            (self.time * 47.0).sin() * (self.time * 29.0).cos(),
+            (self.time * 53.0).cos() * (self.time * 31.0).sin(),
+        ) * self.shake;
+        (self.pos + shake).floor()
+    }
+    /// World position under a pixel of the frame, undoing zoom about the frame's middle
+    pub fn frame_to_world(&self, point: Vec2) -> Vec2 {
+        self.view_pos() + (point - vec2(SCREEN_WIDTH, SCREEN_HEIGHT) / 2.0) / self.zoom
+    }
+    /// The part of the world on screen
+    pub fn view(&self) -> Rect {
+        let half = self.half_view();
+        let pos = self.view_pos() - half;
+        Rect::new(pos.x, pos.y, half.x * 2.0, half.y * 2.0)
+    }
+}
+
+// fraction of the remaining distance to cover this frame, independent of frame rate
+fn smoothing(speed: f32, delta_time: f32) -> f32 {
+    1.0 - (-speed * delta_time).exp()
+}
+
+/// Keeps a view of `half_view` around `pos` inside `bounds`, centring it on any axis the bounds are too small for
+pub fn clamp_to_bounds(pos: Vec2, half_view: Vec2, bounds: Rect) -> Vec2 {
+    let clamp_axis = |pos: f32, half: f32, min: f32, size: f32| {
+        if size <= half * 2.0 {
+            min + size / 2.0
+        } else {
+            pos.clamp(min + half, min + size - half)
+        }
+    };
+    vec2(
+        clamp_axis(pos.x, half_view.x, bounds.x, bounds.w),
+        clamp_axis(pos.y, half_view.y, bounds.y, bounds.h),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A map twice the size of the frame each way
+    const BOUNDS: Rect = Rect::new(0.0, 0.0, SCREEN_WIDTH * 2.0, SCREEN_HEIGHT * 2.0);
+    const HALF_VIEW: Vec2 = Vec2::new(SCREEN_WIDTH / 2.0, SCREEN_HEIGHT / 2.0);
+
+    #[test]
+    fn clamps_at_every_edge() {
+        let middle = vec2(SCREEN_WIDTH, SCREEN_HEIGHT);
+        assert_eq!(clamp_to_bounds(middle, HALF_VIEW, BOUNDS), middle);
+        // top left, then bottom right
+        assert_eq!(clamp_to_bounds(Vec2::ZERO, HALF_VIEW, BOUNDS), HALF_VIEW);
+        assert_eq!(
+            clamp_to_bounds(middle * 2.0, HALF_VIEW, BOUNDS),
+            middle * 2.0 - HALF_VIEW
+        );
+        // one axis at a time
+        assert_eq!(
+            clamp_to_bounds(vec2(-50.0, middle.y), HALF_VIEW, BOUNDS),
+            vec2(HALF_VIEW.x, middle.y)
+        );
+        assert_eq!(
+            clamp_to_bounds(vec2(middle.x, 1000.0), HALF_VIEW, BOUNDS),
+            vec2(middle.x, BOUNDS.h - HALF_VIEW.y)
+        );
+    }
+
+    #[test]
+    fn small_maps_are_centred() {
+        let small = Rect::new(16.0, 32.0, 160.0, 96.0);
+        assert_eq!(
+            clamp_to_bounds(Vec2::ZERO, HALF_VIEW, small),
+            vec2(96.0, 80.0)
+        );
+        // wide enough to scroll across, but not tall enough to scroll down
+        let wide = Rect::new(0.0, 0.0, SCREEN_WIDTH * 2.0, 96.0);
+        assert_eq!(
+            clamp_to_bounds(vec2(2000.0, 500.0), HALF_VIEW, wide),
+            vec2(SCREEN_WIDTH * 2.0 - HALF_VIEW.x, 48.0)
+        );
+    }
+
+    #[test]
+    fn dead_zone_lets_the_player_wander() {
+        let start = vec2(SCREEN_WIDTH, SCREEN_HEIGHT);
+        let mut camera = Camera::new(start);
+        camera.update(start + DEAD_ZONE, BOUNDS, 1.0);
+        assert_eq!(camera.pos, start);
+        // long enough to catch up completely, to where the player is just at the dead zone's edge
+        camera.update(start + vec2(40.0, -30.0), BOUNDS, 10.0);
+        assert!(camera.pos.abs_diff_eq(start + vec2(16.0, -14.0), 0.01));
+    }
+
+    #[test]
+    fn update_keeps_the_view_on_the_map() {
+        let mut camera = Camera::new(HALF_VIEW);
+        camera.update(Vec2::ZERO, BOUNDS, 10.0);
+        assert_eq!(camera.pos, HALF_VIEW);
+        camera.target = CameraTarget::Point(vec2(5000.0, 5000.0));
+        camera.update(vec2(5000.0, 5000.0), BOUNDS, 10.0);
+        assert_eq!(camera.pos, BOUNDS.size() - HALF_VIEW);
+    }
+
+    #[test]
+    fn clicks_land_under_a_zoomed_camera() {
+        let mut camera = Camera::new(vec2(100.5, 50.0));
+        camera.zoom = 2.0;
+        // the middle of the frame is wherever the camera is, snapped to a pixel
+        let middle = vec2(SCREEN_WIDTH, SCREEN_HEIGHT) / 2.0;
+        assert_eq!(camera.frame_to_world(middle), vec2(100.0, 50.0));
+        // and the corners are only half a frame out, zoomed in twice over
+        assert_eq!(
+            camera.frame_to_world(Vec2::ZERO),
+            vec2(100.0, 50.0) - middle / 2.0
+        );
+        assert_eq!(
+            camera.frame_to_world(middle * 2.0),
+            vec2(100.0, 50.0) + middle / 2.0
+        );
+    }
+}
@@ -1,36 +1,153 @@
-use std::iter::Map;
-
 use crate::{
-    assets::{AnimationsGroup, Assets},
+    assets::{AnimationsGroup, Assets, Frame, Map, Sprites},
+    input::{Input, InputAction},
+    lighting::{FIRE_LIGHT, Light, STOVE_LIGHT},
     player::{Direction, Tag},
+    render::{DrawCommand, Renderer, TextureId},
+    transitions::{IRIS, Transition},
     utils::*,
+    voice::Typewriter,
 };
 use macroquad::prelude::*;
 
-pub fn any_interacting(characters: &[Character]) -> Option<usize> {
-    characters.iter().position(|f| {
-        f.interacting || {
-            let action = f.get_action();
-            match &action.0 {
-                ActionCondition::Dialogue(_) => true,
-                _ => false,
+/// How close the player has to be to where an interaction happens, squared
+pub const INTERACT_DISTANCE_SQUARED: f32 = 350.0;
+
+/// The character the player would act on, out of every prompt and talkative guest in range.
+/// Prefers whatever is on the tile the player faces, then whatever is nearest.
+pub fn interaction_target(
+    characters: &[Character],
+    player_pos: Vec2,
+    facing: Direction,
+) -> Option<usize> {
+    let faced_tile = ((player_pos + facing.to_vec2() * 16.0) / 16.0).round();
+    characters
+        .iter()
+        .enumerate()
+        .filter_map(|(index, character)| {
+            let pos = character.interaction_pos()?;
+            let distance = player_pos.distance_squared(pos);
+            if distance > INTERACT_DISTANCE_SQUARED {
+                return None;
             }
+            let tile = vec2(character.x as f32, character.y as f32);
+            let faced = tile == faced_tile || (pos / 16.0).round() == faced_tile;
+            Some((index, !faced, distance))
+        })
+        .min_by(|a, b| a.1.cmp(&b.1).then(a.2.total_cmp(&b.2)))
+        .map(|(index, _, _)| index)
+}
+
+/// Whether the player could stand on a tile, with no wall or solid character in the way
+fn walkable(map: &Map, characters: &[Character], (x, y): (usize, usize)) -> bool {
+    x < map.walls.1
+        && map.walls.0.get(x + y * map.walls.1) == Some(&0)
+        && !blocked_tiles(characters).contains(&(x, y))
+}
+
+/// Tiles the player can't walk through because someone's standing there
+fn blocked_tiles(characters: &[Character]) -> Vec<(usize, usize)> {
+    characters
+        .iter()
+        .filter(|f| f.has_collision)
+        .map(|f| (f.x, f.y))
+        .collect()
+}
+
+/// Where a click sends the player
+pub struct ClickPlan {
+    /// Tiles to walk through, not counting the one the player is on
+    pub path: Vec<(usize, usize)>,
+    /// Character to interact with at the end, if one was clicked
+    pub target: Option<usize>,
+}
+
+/// Where clicking `tile` sends the player from `from`, if they can get there
+pub fn plan_click(
+    map: &Map,
+    characters: &[Character],
+    from: (usize, usize),
+    tile: (usize, usize),
+) -> Option<ClickPlan> {
+    let clicked = characters.iter().position(|character| {
+        character.interaction_pos().is_some_and(|pos| {
+            (character.x, character.y) == tile
+                || (pos / 16.0).round() == vec2(tile.0 as f32, tile.1 as f32)
+        })
+    });
+    let path = match clicked {
+        Some(index) => {
+            path_within_reach(map, characters, from, characters[index].interaction_pos()?)?
+        }
+        None if walkable(map, characters, tile) => {
+            pathfind(map, from, tile, &blocked_tiles(characters))?.0
         }
+        None => return None,
+    };
+    Some(ClickPlan {
+        path: path.into_iter().skip(1).collect(),
+        target: clicked,
     })
 }
-type SuccessorIterator =
-    Map<std::vec::IntoIter<(usize, usize)>, fn((usize, usize)) -> ((usize, usize), usize)>;
 
+/// Shortest path to a tile close enough to interact with something at `pos`, starting with `from`
+fn path_within_reach(
+    map: &Map,
+    characters: &[Character],
+    from: (usize, usize),
+    pos: Vec2,
+) -> Option<Vec<(usize, usize)>> {
+    let centre = (pos / 16.0).round();
+    let blocked = blocked_tiles(characters);
+    let mut best: Option<(Vec<(usize, usize)>, usize)> = None;
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            let tile = centre + vec2(dx as f32, dy as f32);
+            if tile.x < 0.0 || tile.y < 0.0 {
+                continue;
+            }
+            let tile = (tile.x as usize, tile.y as usize);
+            let in_reach = (vec2(tile.0 as f32, tile.1 as f32) * 16.0).distance_squared(pos)
+                <= INTERACT_DISTANCE_SQUARED;
+            // the player's own tile counts even though they're standing on it
+            if !in_reach || (tile != from && !walkable(map, characters, tile)) {
+                continue;
+            }
+            if let Some(path) = pathfind(map, from, tile, &blocked)
+                && best.as_ref().is_none_or(|(_, cost)| path.1 < *cost)
+            {
+                best = Some(path);
+            }
+        }
+    }
+    best.map(|(path, _)| path)
+}
+
+pub fn any_interacting(characters: &[Character]) -> Option<usize> {
+    characters
+        .iter()
+        .position(|f| f.interacting || matches!(f.get_action().0, ActionCondition::Dialogue(_)))
+}
+/// Dialogue on screen gets first go at this frame's press, so advancing a line can't also
+/// set off a prompt out in the world
+pub fn take_dialogue_advance(characters: &[Character], input: &mut Input) -> bool {
+    any_interacting(characters).is_some() && input.take_advance()
+}
+type SuccessorIterator = std::iter::Map<
+    std::vec::IntoIter<(usize, usize)>,
+    fn((usize, usize)) -> ((usize, usize), usize),
+>;
+
+/// Shortest path from `from` to `to` and its length, going around walls and the `blocked` tiles
 pub fn pathfind(
-    assets: &Assets,
+    map: &Map,
     from: (usize, usize),
     to: (usize, usize),
-    player_pos: (usize, usize),
-    has_collision: bool,
+    blocked: &[(usize, usize)],
 ) -> Option<(Vec<(usize, usize)>, usize)> {
     pathfinding::prelude::astar(
         &from,
-        |p| generate_successors(assets, *p, player_pos, has_collision),
+        |p| generate_successors(map, *p, blocked),
         |&(x, y)| {
             ((to.0 as f32 - x as f32).powi(2) + (to.1 as f32 - y as f32).powi(2)).sqrt() as usize
         },
@@ -39,10 +156,9 @@ pub fn pathfind(
 }
 
 fn generate_successors(
-    assets: &Assets,
+    map: &Map,
     pos: (usize, usize),
-    player_pos: (usize, usize),
-    has_collision: bool,
+    blocked: &[(usize, usize)],
 ) -> SuccessorIterator {
     let (x, y) = pos;
     let mut candidates = vec![(x + 1, y), (x, y + 1)];
@@ -53,8 +169,7 @@ fn generate_successors(
         candidates.push((x, y - 1));
     }
     candidates.retain(|(cx, cy)| {
-        (!has_collision || (*cx, *cy) != player_pos)
-            && assets.map.walls.0[cx + cy * assets.map.walls.1] == 0
+        !blocked.contains(&(*cx, *cy)) && map.walls.0[cx + cy * map.walls.1] == 0
     });
     fn map_function(p: (usize, usize)) -> ((usize, usize), usize) {
         (p, 1)
@@ -74,14 +189,22 @@ pub struct Character<'a> {
     pub animation_index: usize,
     pub anim_time: f32,
     pub timer: f32,
-    pub draw_over: bool,
+    /// Added to the character's foot position when depth sorting, for sprites whose base isn't on their tile
+    pub depth_bias: f32,
     pub interact_message: Option<&'static str>,
     pub interacting: bool,
     pub name: &'static str,
     pub moving_to: Option<(usize, usize)>,
     pub direction: Direction,
     pub has_collision: bool,
-    pub draw_offset: Vec2,
+    /// Light given off by the character, positioned relative to its `draw_pos`
+    pub light: Option<Light>,
+    /// Events from animation frames that started playing this frame
+    pub animation_events: Vec<&'a str>,
+    /// Whether snow settles on the character while it's outside
+    pub catches_snow: bool,
+    /// How covered in snow the character is, from 0 to 1
+    pub snow: f32,
 }
 impl<'a> Character<'a> {
     pub fn get_action(&self) -> &(ActionCondition, Action) {
@@ -90,26 +213,75 @@ impl<'a> Character<'a> {
         }
         &self.actions[self.action_index]
     }
-    pub fn draw(&self, assets: &Assets, ctx: &DrawCtx) {
+    /// Prompt and position of the interaction the character is waiting on, if it's waiting on one
+    pub fn interact_prompt(&self) -> Option<(&'static str, Vec2)> {
+        match &self.get_action().0 {
+            ActionCondition::PlayerInteract(text, pos) => Some((text, *pos)),
+            ActionCondition::Else(condition, _) => match condition.as_ref() {
+                ActionCondition::PlayerInteract(text, pos) => Some((text, *pos)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+    /// Where the player interacts with the character from, either for its prompt or to talk to it
+    pub fn interaction_pos(&self) -> Option<Vec2> {
+        match self.interact_prompt() {
+            Some((_, pos)) => Some(pos),
+            None => (self.interact_message.is_some() && !self.interacting).then_some(self.draw_pos),
+        }
+    }
+    /// Starts saying `interact_message`, turned towards whoever is at `from`
+    pub fn talk(&mut self, from: Vec2) {
+        self.interacting = true;
+        let dir = Direction::from_vec2((from - self.draw_pos).normalize(), Vec2::ZERO).name();
+        self.animation_index = self.animation.unwrap().tag_names[dir];
+    }
+    /// World y the character is depth sorted by
+    pub fn depth(&self) -> f32 {
+        self.draw_pos.y + TILE_ANCHOR.y + self.depth_bias
+    }
+    /// Frame showing now and where on screen it's drawn
+    fn current_frame(&self, ctx: &DrawCtx) -> Option<(&Frame, Rect)> {
+        let animation = self.animation?;
         let time = (self.anim_time * 1000.0) as u32;
-        if let Some(animation) = self.animation {
-            draw_texture_ex(
-                &animation.animations[self.animation_index].get_at_time(time),
-                (self.draw_pos.x + self.draw_offset.x) * ctx.scale_factor
-                    + (-ctx.camera_pos.x * ctx.scale_factor
-                        + SCREEN_WIDTH * ctx.scale_factor / 2.0)
-                        .floor(),
-                (self.draw_pos.y + self.draw_offset.y) * ctx.scale_factor
-                    + (-ctx.camera_pos.y * ctx.scale_factor
-                        + SCREEN_HEIGHT * ctx.scale_factor / 2.0)
-                        .floor(),
-                WHITE,
-                DrawTextureParams {
-                    dest_size: Some(
-                        animation.animations[0].get_at_time(0).size() * ctx.scale_factor,
-                    ),
-                    ..Default::default()
-                },
+        let offset = TILE_ANCHOR - animation.pivot;
+        let pos = (self.draw_pos + offset) * ctx.scale_factor
+            + (-ctx.camera_pos * ctx.scale_factor
+                + vec2(SCREEN_WIDTH, SCREEN_HEIGHT) * ctx.scale_factor / 2.0)
+                .floor();
+        let size = animation.frames[0].size * ctx.scale_factor;
+        Some((
+            animation.animations[self.animation_index].get_at_time(time),
+            Rect::new(pos.x, pos.y, size.x, size.y),
+        ))
+    }
+    pub fn draw(&self, ctx: &mut DrawCtx) {
+        if let Some((frame, dest)) = self.current_frame(ctx) {
+            ctx.draw_region(frame.region, dest.x, dest.y, WHITE, dest.size());
+        }
+    }
+    /// Draws a line around the character's sprite, marking it as what interact would act on.
+    /// Characters without a sprite, like the stove that's part of the map, get their tile outlined instead.
+    pub fn draw_outline(&self, ctx: &mut DrawCtx) {
+        let Some((frame, dest)) = self.current_frame(ctx) else {
+            let pos = self.draw_pos * ctx.scale_factor
+                + (-ctx.camera_pos * ctx.scale_factor
+                    + vec2(SCREEN_WIDTH, SCREEN_HEIGHT) * ctx.scale_factor / 2.0)
+                    .floor();
+            let size = 16.0 * ctx.scale_factor;
+            ctx.draw_rectangle_lines(pos.x, pos.y, size, size, ctx.scale_factor, HIGHLIGHT);
+            return;
+        };
+        if let Some(outline) = frame.outline {
+            // the outline has a pixel of room on every side
+            let border = ctx.scale_factor;
+            ctx.draw_region(
+                outline,
+                dest.x - border,
+                dest.y - border,
+                HIGHLIGHT,
+                dest.size() + Vec2::splat(border * 2.0),
             );
         }
     }
@@ -119,38 +291,58 @@ pub enum ActionCondition {
     Else(Box<ActionCondition>, Action),
     AlwaysChange,
     NeverChange,
+    /// The player stood near the point and pressed interact. The text is the prompt, shown after the bound key.
     PlayerInteract(&'static str, Vec2),
     ReachedDestination,
     PlayerHasTag(Tag),
     AnimationFinish,
+    /// Playback of this character's or the player's animation reached a frame with this event
+    AnimationEvent(&'static str),
     Dialogue(&'static str),
     Time(f32),
     PlayerNear(f32),
 }
 #[derive(Clone, Copy)]
 pub enum Action {
-    SetOverlayed(bool),
     SetActionIndex(usize),
     GiveTag(Tag),
     ChangeAnimation(usize),
     Teleport(usize, usize),
     TeleportPlayer(usize, usize),
+    /// Starts or stops the character's animation. Playing a finished once or hold animation
+    /// starts it over, and stopping a loop puts it back on its first frame.
     SetAnimationPlaying(bool),
-    SetAnimationTime(f32),
-    ShowScreen(usize),
-    FadeToScreen(usize),
+    /// Plays a cutscene by name, going in and out with the transition if one is given
+    /// instead of the cutscene's own. Scripts wait for the tag it gives at the end.
+    PlayCutscene(&'static str, Option<Transition>),
     SetInteractMessage(Option<&'static str>),
     MoveTo((usize, usize)),
-    HideScreen,
     SetCollision(bool),
+    SetLight(Option<Light>),
+    /// Eases the storm towards a new intensity, from 0 (calm) to 1 (blizzard)
+    SetStormIntensity(f32),
+    /// Points the camera at the character running the script. The player can't move until it returns.
+    CameraFocusSelf,
+    /// Pans the camera over to a tile
+    CameraPanTo((usize, usize)),
+    CameraZoom(f32),
+    /// Goes back to following the player at normal zoom
+    CameraReturn,
+    /// Shakes the camera by up to this many pixels
+    ShakeCamera(f32),
     SetName(&'static str),
+    /// Plays a sound effect from `SOUNDS` by name
+    PlaySound(&'static str),
     PlayPlayerAnimation(&'static str, Tag),
     Noop,
 }
 
+/// How hard the camera shakes with every knock on the door, in pixels
+const KNOCK_SHAKE: f32 = 2.0;
+
 pub const NOOP_ACTION: (ActionCondition, Action) = (ActionCondition::NeverChange, Action::Noop);
 
-pub static BASE_CHARACTER: Character = Character {
+pub const BASE_CHARACTER: Character = Character {
     draw_pos: Vec2::ZERO,
     actions: Vec::new(),
     animation: None,
@@ -161,34 +353,24 @@ pub static BASE_CHARACTER: Character = Character {
     animation_playing: false,
     anim_time: 0.0,
     timer: 0.0,
-    draw_over: false,
+    depth_bias: 0.0,
     interacting: false,
     interact_message: None,
     moving_to: None,
     name: "",
     direction: Direction::Left,
     has_collision: true,
-    draw_offset: Vec2::ZERO,
+    animation_events: Vec::new(),
+    light: None,
+    catches_snow: false,
+    snow: 0.0,
 };
 
-pub fn raincoat_ferret<'a>((x, y): (usize, usize), assets: &'a Assets) -> Character<'a> {
+pub fn raincoat_ferret<'a>((x, y): (usize, usize), assets: &'a Assets, map: &Map) -> Character<'a> {
     Character {
         draw_pos: vec2(x as f32, y as f32) * 16.0,
         actions: vec![
             (ActionCondition::PlayerHasTag(Tag::OpenedDoor), Action::Noop),
-            (ActionCondition::Time(0.8), Action::Noop),
-            (
-                ActionCondition::Dialogue(
-                    "Hello kind stranger! I have lost my way\nin the snowstorm. It is cold and dark.",
-                ),
-                Action::Noop,
-            ),
-            (
-                ActionCondition::Dialogue("Can I please come inside?"),
-                Action::Noop,
-            ),
-            (ActionCondition::Time(0.5), Action::ShowScreen(1)),
-            (ActionCondition::Time(1.0), Action::HideScreen),
             (
                 ActionCondition::AlwaysChange,
                 Action::TeleportPlayer(x + 1, y + 1),
@@ -199,6 +381,8 @@ pub fn raincoat_ferret<'a>((x, y): (usize, usize), assets: &'a Assets) -> Charac
                 ActionCondition::AlwaysChange,
                 Action::GiveTag(Tag::ClosedDoor),
             ),
+            (ActionCondition::AlwaysChange, Action::CameraFocusSelf),
+            (ActionCondition::AlwaysChange, Action::CameraZoom(2.0)),
             (
                 ActionCondition::AlwaysChange,
                 Action::SetAnimationPlaying(true),
@@ -206,7 +390,7 @@ pub fn raincoat_ferret<'a>((x, y): (usize, usize), assets: &'a Assets) -> Charac
             (ActionCondition::AnimationFinish, Action::ChangeAnimation(3)),
             (
                 ActionCondition::AlwaysChange,
-                Action::MoveTo(assets.map.special.find_tile(4)),
+                Action::MoveTo(map.special.find_tile(4)),
             ),
             (
                 ActionCondition::ReachedDestination,
@@ -214,16 +398,15 @@ pub fn raincoat_ferret<'a>((x, y): (usize, usize), assets: &'a Assets) -> Charac
                     "Could you please light the fireplace?\nI'm so cold.",
                 )),
             ),
+            (ActionCondition::AlwaysChange, Action::CameraReturn),
             (
                 ActionCondition::AlwaysChange,
                 Action::SetAnimationPlaying(false),
             ),
-            (ActionCondition::AlwaysChange, Action::SetAnimationTime(0.0)),
             (
                 ActionCondition::PlayerHasTag(Tag::LightFire),
                 Action::SetInteractMessage(None),
             ),
-            (ActionCondition::AlwaysChange, Action::SetAnimationTime(0.0)),
             (ActionCondition::AlwaysChange, Action::ChangeAnimation(6)),
             (
                 ActionCondition::AlwaysChange,
@@ -232,7 +415,7 @@ pub fn raincoat_ferret<'a>((x, y): (usize, usize), assets: &'a Assets) -> Charac
             (ActionCondition::AnimationFinish, Action::Noop),
             (
                 ActionCondition::AlwaysChange,
-                Action::MoveTo(assets.map.special.find_tile(5)),
+                Action::MoveTo(map.special.find_tile(5)),
             ),
             (
                 ActionCondition::ReachedDestination,
@@ -251,17 +434,24 @@ pub fn raincoat_ferret<'a>((x, y): (usize, usize), assets: &'a Assets) -> Charac
                 ActionCondition::Time(2.0),
                 Action::GiveTag(Tag::FamilyShouldArrive),
             ),
+            (
+                ActionCondition::AlwaysChange,
+                Action::SetStormIntensity(1.0),
+            ),
+            // show the door while the family knocks
+            (ActionCondition::AlwaysChange, Action::CameraPanTo((x, y))),
+            (ActionCondition::Time(2.5), Action::CameraReturn),
         ],
-        animation: Some(&assets.raincoat_ferret),
+        animation: Some(assets.sprite("raincoat_ferret")),
         x,
         y,
         name: "Ferret in a raincoat",
-        draw_offset: vec2(-16.0, -16.0),
+        catches_snow: true,
         ..BASE_CHARACTER
     }
 }
-pub fn mother_ferret<'a>(assets: &'a Assets) -> Character<'a> {
-    let (x, y) = assets.map.special.find_tile(1);
+pub fn mother_ferret<'a>(assets: &'a Assets, map: &Map) -> Character<'a> {
+    let (x, y) = map.special.find_tile(1);
     Character {
         draw_pos: vec2(0 as f32, 0 as f32) * 16.0,
         actions: vec![
@@ -273,21 +463,6 @@ pub fn mother_ferret<'a>(assets: &'a Assets) -> Character<'a> {
                 ActionCondition::PlayerHasTag(Tag::OpenedDoor2),
                 Action::Noop,
             ),
-            (ActionCondition::Time(0.8), Action::Noop),
-            (
-                ActionCondition::Dialogue(
-                    "Hi! My boys and I were out playing when this\nstorm struck!",
-                ),
-                Action::Noop,
-            ),
-            (
-                ActionCondition::Dialogue(
-                    "They are cold and tired. May we please\ncome inside and ride out the storm?",
-                ),
-                Action::Noop,
-            ),
-            (ActionCondition::Time(0.5), Action::ShowScreen(3)),
-            (ActionCondition::Time(1.0), Action::HideScreen),
             (
                 ActionCondition::AlwaysChange,
                 Action::TeleportPlayer(x + 1, y + 1),
@@ -297,6 +472,7 @@ pub fn mother_ferret<'a>(assets: &'a Assets) -> Character<'a> {
                 ActionCondition::AlwaysChange,
                 Action::GiveTag(Tag::ClosedDoor2),
             ),
+            (ActionCondition::AlwaysChange, Action::CameraFocusSelf),
             (ActionCondition::Time(3.0), Action::Noop),
             (
                 ActionCondition::AlwaysChange,
@@ -304,16 +480,15 @@ pub fn mother_ferret<'a>(assets: &'a Assets) -> Character<'a> {
             ),
             (
                 ActionCondition::AlwaysChange,
-                Action::MoveTo(assets.map.special.find_tile(6)),
+                Action::MoveTo(map.special.find_tile(6)),
             ),
             (
                 ActionCondition::ReachedDestination,
                 Action::SetAnimationPlaying(false),
             ),
-            (ActionCondition::AlwaysChange, Action::SetAnimationTime(0.0)),
+            (ActionCondition::AlwaysChange, Action::CameraReturn),
             (ActionCondition::Time(0.5), Action::ChangeAnimation(4)),
             (ActionCondition::AlwaysChange, Action::SetCollision(false)),
-            (ActionCondition::AlwaysChange, Action::SetOverlayed(true)),
             (
                 ActionCondition::AlwaysChange,
                 Action::SetAnimationPlaying(true),
@@ -326,19 +501,23 @@ pub fn mother_ferret<'a>(assets: &'a Assets) -> Character<'a> {
                 ActionCondition::Time(2.0),
                 Action::GiveTag(Tag::ChildrenWantChocolate),
             ),
+            (
+                ActionCondition::AlwaysChange,
+                Action::SetStormIntensity(0.3),
+            ),
         ],
         animation_index: 3,
         name: "Ferret Mother",
-        animation: Some(&assets.mother_ferret),
+        animation: Some(assets.sprite("mother_ferret")),
         x,
         y,
-        draw_offset: vec2(-16.0, -32.0),
+        catches_snow: true,
         ..BASE_CHARACTER
     }
 }
-pub fn child_ferret<'a>(assets: &'a Assets, id: usize) -> Character<'a> {
-    let (x, y) = assets.map.special.find_tile(1);
-    let mut play_pos = assets.map.special.find_tile(4);
+pub fn child_ferret<'a>(assets: &'a Assets, map: &Map, id: usize) -> Character<'a> {
+    let (x, y) = map.special.find_tile(1);
+    let mut play_pos = map.special.find_tile(4);
     play_pos.0 += 1;
 
     let mut actions = vec![
@@ -403,7 +582,8 @@ pub fn child_ferret<'a>(assets: &'a Assets, id: usize) -> Character<'a> {
             ),
             (
                 ActionCondition::PlayerNear(3.0 * 16.0),
-                Action::FadeToScreen(4),
+                // closing in on the family gathered round
+                Action::PlayCutscene("hot_chocolate", Some(IRIS)),
             ),
         ];
         actions.append(&mut new);
@@ -413,11 +593,11 @@ pub fn child_ferret<'a>(assets: &'a Assets, id: usize) -> Character<'a> {
         draw_pos: vec2(0 as f32, 0 as f32) * 16.0,
         actions,
         name: "Child Ferret",
-        animation: Some(&assets.child_ferret[id]),
+        animation: Some(assets.sprite(&format!("child_ferret{}", id + 1))),
         has_collision: false,
-        draw_offset: vec2(-5.0 + 10.0 * id as f32 + 3.0, -3.0 * id as f32 + 1.0),
         x,
         y,
+        catches_snow: true,
         ..BASE_CHARACTER
     }
 }
@@ -425,78 +605,83 @@ pub fn door<'a>((x, y): (usize, usize), assets: &'a Assets) -> Character<'a> {
     Character {
         draw_pos: vec2(x as f32, y as f32) * 16.0,
         actions: vec![
+            (ActionCondition::AlwaysChange, Action::PlaySound("knock")),
+            (
+                ActionCondition::AlwaysChange,
+                Action::ShakeCamera(KNOCK_SHAKE),
+            ),
+            (ActionCondition::Time(0.3), Action::ShakeCamera(KNOCK_SHAKE)),
+            (ActionCondition::Time(0.3), Action::ShakeCamera(KNOCK_SHAKE)),
             (
                 ActionCondition::Dialogue("*knock* *knock* *knock*"),
                 Action::Noop,
             ),
             (
-                ActionCondition::PlayerInteract(
-                    "E: open door",
-                    vec2(x as f32, (y + 1) as f32) * 16.0,
-                ),
+                ActionCondition::PlayerInteract("open door", vec2(x as f32, (y + 1) as f32) * 16.0),
                 Action::SetAnimationPlaying(true),
             ),
             (
-                ActionCondition::AnimationFinish,
+                ActionCondition::AnimationEvent("event:door_open"),
                 Action::SetAnimationPlaying(false),
             ),
             (
                 ActionCondition::AlwaysChange,
-                Action::GiveTag(Tag::OpenedDoor),
+                Action::PlayCutscene("raincoat_at_door", None),
             ),
-            (ActionCondition::AlwaysChange, Action::ShowScreen(0)),
             (
                 ActionCondition::PlayerHasTag(Tag::ClosedDoor),
-                Action::SetAnimationTime(0.0),
+                Action::SetAnimationPlaying(true),
             ),
             (
                 ActionCondition::PlayerHasTag(Tag::FamilyShouldArrive),
                 Action::Noop,
             ),
+            (ActionCondition::AlwaysChange, Action::PlaySound("knock")),
+            (
+                ActionCondition::AlwaysChange,
+                Action::ShakeCamera(KNOCK_SHAKE),
+            ),
+            (ActionCondition::Time(0.3), Action::ShakeCamera(KNOCK_SHAKE)),
+            (ActionCondition::Time(0.3), Action::ShakeCamera(KNOCK_SHAKE)),
             (
                 ActionCondition::Dialogue("*knock* *knock* *knock*"),
                 Action::Noop,
             ),
             (
-                ActionCondition::PlayerInteract(
-                    "E: open door",
-                    vec2(x as f32, (y + 1) as f32) * 16.0,
-                ),
+                ActionCondition::PlayerInteract("open door", vec2(x as f32, (y + 1) as f32) * 16.0),
                 Action::SetAnimationPlaying(true),
             ),
             (
-                ActionCondition::AnimationFinish,
+                ActionCondition::AnimationEvent("event:door_open"),
                 Action::SetAnimationPlaying(false),
             ),
             (
                 ActionCondition::AlwaysChange,
-                Action::GiveTag(Tag::OpenedDoor2),
+                Action::PlayCutscene("family_at_door", None),
             ),
-            (ActionCondition::AlwaysChange, Action::ShowScreen(2)),
             (
                 ActionCondition::PlayerHasTag(Tag::ClosedDoor2),
-                Action::SetAnimationTime(0.0),
+                Action::SetAnimationPlaying(true),
             ),
         ],
         name: "Door",
-        animation: Some(&assets.door),
+        animation: Some(assets.sprite("door")),
         x,
         y,
         ..BASE_CHARACTER
     }
 }
 #[allow(dead_code)]
-pub fn test_character<'a>((x, y): (usize, usize), assets: &'a Assets) -> Character<'a> {
+pub fn test_character<'a>((x, y): (usize, usize), assets: &'a Assets, map: &Map) -> Character<'a> {
     Character {
         draw_pos: vec2(x as f32, y as f32) * 16.0,
         actions: vec![(
             ActionCondition::AlwaysChange,
-            Action::MoveTo(assets.map.special.find_tile(0)),
+            Action::MoveTo(map.special.find_tile(0)),
         )],
-        animation: Some(&assets.raincoat_ferret),
+        animation: Some(assets.sprite("raincoat_ferret")),
         x,
         y,
-        draw_over: false,
         ..BASE_CHARACTER
     }
 }
@@ -510,13 +695,14 @@ pub fn stove<'a>((x, y): (usize, usize)) -> Character<'a> {
             ),
             (
                 ActionCondition::PlayerInteract(
-                    "E: make hot chocolate",
+                    "make hot chocolate",
                     vec2(x as f32, y as f32) * 16.0,
                 ),
                 Action::PlayPlayerAnimation("make hot cocoa", Tag::CarryingCups),
             ),
         ],
         animation: None,
+        light: Some(STOVE_LIGHT),
         x,
         y,
         ..BASE_CHARACTER
@@ -529,21 +715,27 @@ pub fn fireplace<'a>((x, y): (usize, usize), assets: &'a Assets) -> Character<'a
             (ActionCondition::PlayerHasTag(Tag::ClosedDoor), Action::Noop),
             (
                 ActionCondition::PlayerInteract(
-                    "E: light fireplace",
+                    "light fireplace",
                     vec2(x as f32 + 0.5, (y + 2) as f32) * 16.0,
                 ),
                 Action::SetAnimationPlaying(true),
             ),
             (ActionCondition::AlwaysChange, Action::ChangeAnimation(1)),
+            (ActionCondition::AlwaysChange, Action::PlaySound("ignite")),
+            (
+                ActionCondition::AlwaysChange,
+                Action::SetLight(Some(FIRE_LIGHT)),
+            ),
             (
                 ActionCondition::AlwaysChange,
                 Action::GiveTag(Tag::LightFire),
             ),
         ],
-        animation: Some(&assets.fireplace),
+        animation: Some(assets.sprite("fireplace")),
         x,
         y,
-        draw_over: true,
+        // the hearth reaches two tiles below the fireplace's own tile
+        depth_bias: 32.0,
         ..BASE_CHARACTER
     }
 }
@@ -552,29 +744,84 @@ pub struct DrawCtx<'a> {
     pub screen_size: Vec2,
     pub camera_pos: Vec2,
     pub scale_factor: f32,
-    pub assets: &'a Assets,
+    pub sprites: &'a Sprites,
+    pub renderer: &'a mut dyn Renderer,
+    pub typewriter: &'a mut Typewriter,
+    pub input: &'a mut Input,
+}
+impl DrawCtx<'_> {
+    /// Draws a region of the atlas, like a frame of an animation
+    pub fn draw_region(&mut self, region: usize, x: f32, y: f32, color: Color, dest_size: Vec2) {
+        let (page, source) = self.sprites.regions[region];
+        self.renderer.draw(DrawCommand::Texture {
+            texture: TextureId::AtlasPage(page),
+            source: Some(source),
+            dest: Rect::new(x, y, dest_size.x, dest_size.y),
+            color,
+        });
+    }
+    pub fn draw_rectangle(&mut self, x: f32, y: f32, w: f32, h: f32, color: Color) {
+        self.renderer.draw(DrawCommand::Rect {
+            rect: Rect::new(x, y, w, h),
+            color,
+        });
+    }
+    pub fn draw_rectangle_lines(
+        &mut self,
+        x: f32,
+        y: f32,
+        w: f32,
+        h: f32,
+        thickness: f32,
+        color: Color,
+    ) {
+        self.renderer.draw(DrawCommand::RectLines {
+            rect: Rect::new(x, y, w, h),
+            thickness,
+            color,
+        });
+    }
+    /// Size of `text` in the UI font
+    pub fn measure_text(&self, text: &str, font_size: u16) -> TextDimensions {
+        self.renderer.measure_text(text, font_size)
+    }
+    /// Draws white text in the UI font
+    pub fn draw_text(&mut self, text: &str, x: f32, y: f32, font_size: u16) {
+        self.renderer.draw(DrawCommand::Text {
+            text: text.to_string(),
+            pos: vec2(x, y),
+            font_size,
+            color: WHITE,
+        });
+    }
 }
 
 pub const DARK_BLUE: Color = Color::from_hex(0x143464);
 pub const DIALOGUE_BORDER: Color = Color::from_hex(0xbb7547);
 pub const DIALOGUE_BODY: Color = Color::from_hex(0x3b1725);
+/// Outline around whatever interact would act on
+pub const HIGHLIGHT: Color = Color::from_hex(0xf6e7c1);
 
-pub fn draw_dialogue(text: &str, name: &str, ctx: &DrawCtx) -> bool {
+/// Draws dialogue revealing a character at a time. Returns true once it's fully shown and `advance` is set,
+/// advancing before then shows the rest straight away.
+pub fn draw_dialogue(text: &str, name: &str, advance: bool, ctx: &mut DrawCtx) -> bool {
+    let revealed = ctx.typewriter.reveal(text, name);
+    let shown: String = text.chars().take(revealed).collect();
     let w = 200.0 * ctx.scale_factor;
     let h = 30.0 * ctx.scale_factor;
     let x = (ctx.screen_size.x - w) - 20.0 * ctx.scale_factor;
     let y = ctx.screen_size.y - h - 5.0 * ctx.scale_factor;
-    draw_rectangle(x, y, w, h, DIALOGUE_BODY);
-    draw_rectangle_lines(x, y, w, h, 2.0 * ctx.scale_factor, DIALOGUE_BORDER);
+    ctx.draw_rectangle(x, y, w, h, DIALOGUE_BODY);
+    ctx.draw_rectangle_lines(x, y, w, h, 2.0 * ctx.scale_factor, DIALOGUE_BORDER);
     let nameplate_height = 10.0 * ctx.scale_factor;
-    draw_rectangle(
+    ctx.draw_rectangle(
         x,
         y - nameplate_height + 1.0 * ctx.scale_factor,
         80.0 * ctx.scale_factor,
         nameplate_height,
         DIALOGUE_BODY,
     );
-    draw_rectangle_lines(
+    ctx.draw_rectangle_lines(
         x,
         y - nameplate_height + 1.0 * ctx.scale_factor,
         80.0 * ctx.scale_factor,
@@ -582,71 +829,195 @@ pub fn draw_dialogue(text: &str, name: &str, ctx: &DrawCtx) -> bool {
         2.0 * ctx.scale_factor,
         DIALOGUE_BORDER,
     );
-    draw_text_ex(
+    ctx.draw_text(
         name,
         x + 1.0 * ctx.scale_factor,
         y - 2.0 * ctx.scale_factor,
-        TextParams {
-            font: Some(&ctx.assets.font),
-            font_size: (8.0 * ctx.scale_factor) as u16,
-            ..Default::default()
-        },
+        (8.0 * ctx.scale_factor) as u16,
     );
-    draw_multiline_text_ex(
-        text,
+    ctx.draw_text(
+        &shown,
         x + 5.0 * ctx.scale_factor,
         y + 12.0 * ctx.scale_factor,
-        None,
-        TextParams {
-            font: Some(&ctx.assets.font),
-            font_size: (10.0 * ctx.scale_factor) as u16,
-            ..Default::default()
-        },
+        (10.0 * ctx.scale_factor) as u16,
     );
-    draw_rectangle(
-        x + w - 12.0 * ctx.scale_factor,
+    // badge with the interact key, widening for longer key names
+    let key = ctx.input.key_name(InputAction::Interact);
+    let key_font_size = (8.0 * ctx.scale_factor) as u16;
+    let key_width = ctx.measure_text(key, key_font_size).width;
+    let badge_width = (12.0 * ctx.scale_factor).max(key_width + 4.0 * ctx.scale_factor);
+    ctx.draw_rectangle(
+        x + w - badge_width,
         y + h - 10.0 * ctx.scale_factor,
-        12.0 * ctx.scale_factor,
+        badge_width,
         nameplate_height,
         DIALOGUE_BODY,
     );
-    draw_rectangle_lines(
-        x + w - 12.0 * ctx.scale_factor,
+    ctx.draw_rectangle_lines(
+        x + w - badge_width,
         y + h - 10.0 * ctx.scale_factor,
-        12.0 * ctx.scale_factor,
+        badge_width,
         nameplate_height,
         2.0 * ctx.scale_factor,
         DIALOGUE_BORDER,
     );
-    draw_text_ex(
-        "E",
-        x + w - 10.0 * ctx.scale_factor,
+    ctx.draw_text(
+        key,
+        x + w - badge_width + 2.0 * ctx.scale_factor,
         y + h - 3.0 * ctx.scale_factor,
-        TextParams {
-            font: Some(&ctx.assets.font),
-            font_size: (8.0 * ctx.scale_factor) as u16,
-            ..Default::default()
-        },
+        key_font_size,
     );
-    is_key_pressed(KeyCode::E)
+    if !advance {
+        return false;
+    }
+    if revealed < text.chars().count() {
+        ctx.typewriter.finish(text);
+        return false;
+    }
+    true
 }
 
-pub fn draw_tooltip(text: &str, ctx: &DrawCtx) -> bool {
+/// Draws a prompt like "E: open door" with whatever key interact is bound to. Returns true when it's pressed.
+pub fn draw_tooltip(text: &str, ctx: &mut DrawCtx) -> bool {
+    let text = format!("{}: {text}", ctx.input.key_name(InputAction::Interact));
     let w = 150.0 * ctx.scale_factor;
     let h = 20.0 * ctx.scale_factor;
     let x = (ctx.screen_size.x - w) / 2.0;
     let y = ctx.screen_size.y - h - 5.0 * ctx.scale_factor;
-    draw_rectangle(x, y, w, h, DARK_BLUE);
-    draw_rectangle_lines(x, y, w, h, 2.0 * ctx.scale_factor, WHITE);
-    draw_text_ex(
-        text,
+    ctx.draw_rectangle(x, y, w, h, DARK_BLUE);
+    ctx.draw_rectangle_lines(x, y, w, h, 2.0 * ctx.scale_factor, WHITE);
+    ctx.draw_text(
+        &text,
         x + 5.0 * ctx.scale_factor,
         y + 12.0 * ctx.scale_factor,
-        TextParams {
-            font: Some(&ctx.assets.font),
-            font_size: (12.0 * ctx.scale_factor) as u16,
-            ..Default::default()
-        },
+        (12.0 * ctx.scale_factor) as u16,
     );
-    is_key_pressed(KeyCode::E)
+    ctx.input.take(InputAction::Interact)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assets::TileMap, input::default_bindings};
+
+    #[test]
+    fn dialogue_takes_the_press_first() {
+        let mut input = Input::idle(&default_bindings());
+        input.press(InputAction::Interact);
+        let guest = Character {
+            interacting: true,
+            interact_message: Some("Brr."),
+            ..BASE_CHARACTER
+        };
+        let door = Character {
+            actions: vec![(
+                ActionCondition::PlayerInteract("open door", Vec2::ZERO),
+                Action::Noop,
+            )],
+            ..BASE_CHARACTER
+        };
+        // the door comes first in the list but the guest talking still gets the press
+        assert!(take_dialogue_advance(&[door, guest], &mut input));
+        assert!(!input.take(InputAction::Interact));
+    }
+
+    #[test]
+    fn the_world_keeps_the_press_without_dialogue() {
+        let mut input = Input::idle(&default_bindings());
+        input.press(InputAction::Interact);
+        assert!(!take_dialogue_advance(&[BASE_CHARACTER], &mut input));
+        assert!(input.take(InputAction::Interact));
+    }
+
+    #[test]
+    fn clicks_walk_around_guests() {
+        let map = Map::headless(TileMap::from_rows(&["#####", "#...#", "#...#", "#####"]));
+        let guest = Character {
+            x: 2,
+            y: 1,
+            ..BASE_CHARACTER
+        };
+        let plan = plan_click(&map, &[guest], (1, 1), (3, 1)).unwrap();
+        assert_eq!(plan.path, vec![(1, 2), (2, 2), (3, 2), (3, 1)]);
+        assert_eq!(plan.target, None);
+    }
+
+    /// Someone on a tile who has something to say
+    fn guest(x: usize, y: usize) -> Character<'static> {
+        Character {
+            x,
+            y,
+            draw_pos: vec2(x as f32, y as f32) * 16.0,
+            interact_message: Some("Brr."),
+            ..BASE_CHARACTER
+        }
+    }
+
+    /// A prompt at `pos`, on a character standing somewhere out of the way
+    fn prompt(pos: Vec2) -> Character<'static> {
+        Character {
+            actions: vec![(
+                ActionCondition::PlayerInteract("open door", pos),
+                Action::Noop,
+            )],
+            ..BASE_CHARACTER
+        }
+    }
+
+    const PLAYER: Vec2 = Vec2::new(80.0, 80.0);
+
+    #[test]
+    fn facing_beats_being_nearer() {
+        // the guest is a whole tile below, the prompt half a tile to the right
+        let characters = [guest(5, 6), prompt(PLAYER + vec2(8.0, 0.0))];
+        assert_eq!(
+            interaction_target(&characters, PLAYER, Direction::Down),
+            Some(0)
+        );
+        assert_eq!(
+            interaction_target(&characters, PLAYER, Direction::Right),
+            Some(1)
+        );
+        // facing neither, so the nearer one
+        assert_eq!(
+            interaction_target(&characters, PLAYER, Direction::Up),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn ties_go_to_the_nearest() {
+        // both are on the tile below, the prompt just nearer
+        let characters = [guest(5, 6), prompt(PLAYER + vec2(0.0, 12.0))];
+        assert_eq!(
+            interaction_target(&characters, PLAYER, Direction::Down),
+            Some(1)
+        );
+        // and facing away from both
+        assert_eq!(
+            interaction_target(&characters, PLAYER, Direction::Up),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn just_out_of_reach() {
+        let at = |distance_squared: f32| [prompt(PLAYER + vec2(distance_squared.sqrt(), 0.0))];
+        assert_eq!(
+            interaction_target(
+                &at(INTERACT_DISTANCE_SQUARED + 1.0),
+                PLAYER,
+                Direction::Right
+            ),
+            None
+        );
+        assert_eq!(
+            interaction_target(
+                &at(INTERACT_DISTANCE_SQUARED - 1.0),
+                PLAYER,
+                Direction::Right
+            ),
+            Some(0)
+        );
+    }
 }
@@ -0,0 +1,370 @@
+use macroquad::prelude::*;
+
+use crate::{
+    characters::{DrawCtx, draw_dialogue},
+    player::Tag,
+    transitions::{ActiveTransition, DISSOLVE, FADE, IRIS, SLIDE, Transition},
+    utils::{SCREEN_HEIGHT, SCREEN_WIDTH},
+};
+
+/// Screens are drawn this many times their size, to fill the frame
+const SCREEN_SCALE: f32 = 4.0;
+
+/// Slow zoom and pan over a screen while its panel shows
+#[derive(Clone, Copy)]
+pub struct KenBurns {
+    pub from_zoom: f32,
+    pub to_zoom: f32,
+    /// Offset of the screen from the middle of the frame, in pixels
+    pub from: Vec2,
+    pub to: Vec2,
+}
+pub const STILL: KenBurns = KenBurns {
+    from_zoom: 1.0,
+    to_zoom: 1.0,
+    from: Vec2::ZERO,
+    to: Vec2::ZERO,
+};
+impl KenBurns {
+    /// Zoom and offset `progress` of the way through, from 0 to 1
+    pub fn at(&self, progress: f32) -> (f32, Vec2) {
+        let progress = progress.clamp(0.0, 1.0);
+        (
+            self.from_zoom + (self.to_zoom - self.from_zoom) * progress,
+            self.from.lerp(self.to, progress),
+        )
+    }
+}
+
+pub struct Caption {
+    pub speaker: &'static str,
+    pub text: &'static str,
+}
+
+pub struct Panel {
+    /// Screen to show, or just the caption on black
+    pub screen: Option<&'static str>,
+    pub caption: Option<Caption>,
+    /// Seconds the panel shows for at least, and how long its ken burns takes
+    pub duration: f32,
+    /// Keep showing after `duration` until the player presses on
+    pub wait_for_input: bool,
+    pub ken_burns: KenBurns,
+}
+
+/// What happens once a cutscene is over
+#[derive(Clone, Copy)]
+pub enum CutsceneEnd {
+    /// Back to the house, giving the player a tag for scripts to wait on
+    GiveTag(Tag),
+    /// Straight on to another cutscene
+    Play(&'static str),
+    /// Back to the start of the game
+    Restart,
+}
+
+pub struct Cutscene {
+    pub panels: &'static [Panel],
+    /// Used going into and out of the cutscene
+    pub transition: Transition,
+    /// Used between panels with different screens. Panels sharing a screen just swap captions.
+    pub panel_transition: Transition,
+    pub end: CutsceneEnd,
+}
+
+pub const CUTSCENES: &[(&str, Cutscene)] = &[
+    (
+        "raincoat_at_door",
+        Cutscene {
+            panels: &[
+                Panel {
+                    screen: Some("raincoat_ferret"),
+                    caption: Some(Caption {
+                        speaker: "Ferret in a raincoat",
+                        text: "Hello kind stranger! I have lost my way\nin the snowstorm. It is cold and dark.",
+                    }),
+                    duration: 0.8,
+                    wait_for_input: true,
+                    ken_burns: STILL,
+                },
+                Panel {
+                    screen: Some("raincoat_ferret"),
+                    caption: Some(Caption {
+                        speaker: "Ferret in a raincoat",
+                        text: "Can I please come inside?",
+                    }),
+                    duration: 0.0,
+                    wait_for_input: true,
+                    ken_burns: STILL,
+                },
+                Panel {
+                    screen: Some("raincoat_ferret_happy"),
+                    caption: None,
+                    duration: 1.5,
+                    wait_for_input: false,
+                    ken_burns: KenBurns {
+                        from_zoom: 1.0,
+                        to_zoom: 1.1,
+                        from: Vec2::ZERO,
+                        to: Vec2::new(0.0, 8.0),
+                    },
+                },
+            ],
+            transition: IRIS,
+            panel_transition: DISSOLVE,
+            end: CutsceneEnd::GiveTag(Tag::OpenedDoor),
+        },
+    ),
+    (
+        "family_at_door",
+        Cutscene {
+            panels: &[
+                Panel {
+                    screen: Some("ferret_family"),
+                    caption: Some(Caption {
+                        speaker: "Ferret Mother",
+                        text: "Hi! My boys and I were out playing when this\nstorm struck!",
+                    }),
+                    duration: 0.8,
+                    wait_for_input: true,
+                    ken_burns: STILL,
+                },
+                Panel {
+                    screen: Some("ferret_family"),
+                    caption: Some(Caption {
+                        speaker: "Ferret Mother",
+                        text: "They are cold and tired. May we please\ncome inside and ride out the storm?",
+                    }),
+                    duration: 0.0,
+                    wait_for_input: true,
+                    ken_burns: STILL,
+                },
+                Panel {
+                    screen: Some("ferret_family_happy"),
+                    caption: None,
+                    duration: 1.5,
+                    wait_for_input: false,
+                    ken_burns: KenBurns {
+                        from_zoom: 1.1,
+                        to_zoom: 1.0,
+                        from: Vec2::ZERO,
+                        to: Vec2::ZERO,
+                    },
+                },
+            ],
+            transition: SLIDE,
+            panel_transition: DISSOLVE,
+            end: CutsceneEnd::GiveTag(Tag::OpenedDoor2),
+        },
+    ),
+    (
+        "hot_chocolate",
+        Cutscene {
+            panels: &[Panel {
+                screen: Some("child_ferrets_hot_chocolate"),
+                caption: None,
+                duration: 6.0,
+                wait_for_input: false,
+                ken_burns: KenBurns {
+                    from_zoom: 1.15,
+                    to_zoom: 1.0,
+                    from: Vec2::new(-24.0, 0.0),
+                    to: Vec2::ZERO,
+                },
+            }],
+            transition: FADE,
+            panel_transition: FADE,
+            end: CutsceneEnd::Play("credits"),
+        },
+    ),
+    (
+        "credits",
+        Cutscene {
+            panels: &[
+                Panel {
+                    screen: None,
+                    caption: Some(Caption {
+                        speaker: "",
+                        text: "winternight",
+                    }),
+                    duration: 3.0,
+                    wait_for_input: false,
+                    ken_burns: STILL,
+                },
+                Panel {
+                    screen: None,
+                    caption: Some(Caption {
+                        speaker: "",
+                        text: "Made in Rust for Hackclub's Siege, week 13",
+                    }),
+                    duration: 3.0,
+                    wait_for_input: false,
+                    ken_burns: STILL,
+                },
+                Panel {
+                    screen: None,
+                    caption: Some(Caption {
+                        speaker: "",
+                        text: "Thanks for playing!",
+                    }),
+                    duration: 2.0,
+                    wait_for_input: true,
+                    ken_burns: STILL,
+                },
+            ],
+            transition: FADE,
+            panel_transition: DISSOLVE,
+            end: CutsceneEnd::Restart,
+        },
+    ),
+];
+
+pub fn cutscene(name: &str) -> &'static Cutscene {
+    CUTSCENES
+        .iter()
+        .find(|(f, _)| *f == name)
+        .map(|(_, f)| f)
+        .unwrap_or_else(|| panic!("no cutscene named {name:?}"))
+}
+
+/// A cutscene that's playing
+pub struct CutscenePlayer {
+    pub cutscene: &'static Cutscene,
+    /// Panel on screen, or None while the house still shows on the way in or out
+    pub panel: Option<usize>,
+    /// Seconds the current panel has been showing
+    pub time: f32,
+    /// Transition playing, switching to a panel or back out of the cutscene
+    pub transition: Option<ActiveTransition<Option<usize>>>,
+    /// Used going into and out of the cutscene, its own unless whatever started it picked another
+    pub in_out: Transition,
+}
+impl CutscenePlayer {
+    /// Starts playing `cutscene`, going in and out with `transition` instead of its own if there is one
+    pub fn new(cutscene: &'static Cutscene, transition: Option<Transition>) -> Self {
+        let in_out = transition.unwrap_or(cutscene.transition);
+        Self {
+            cutscene,
+            panel: None,
+            time: 0.0,
+            transition: Some(ActiveTransition::new(in_out, Some(0))),
+            in_out,
+        }
+    }
+    /// Starts with the screen already covered, for following straight on from another cutscene
+    pub fn covered(cutscene: &'static Cutscene) -> Self {
+        Self {
+            transition: Some(ActiveTransition::covered(cutscene.transition, Some(0))),
+            ..Self::new(cutscene, None)
+        }
+    }
+    /// Steps the cutscene forward. `advance` moves past panels waiting for input,
+    /// and `skip` leaves the cutscene altogether.
+    ///
+    /// Returns true on the frame the cutscene is left, while the screen is still covered.
+    pub fn update(&mut self, delta_time: f32, advance: bool, skip: bool) -> bool {
+        self.time += delta_time;
+        if let Some(transition) = &mut self.transition {
+            let mut left = false;
+            if transition.update(delta_time) {
+                self.panel = transition.target;
+                self.time = 0.0;
+                left = self.panel.is_none();
+            }
+            if transition.is_finished() {
+                self.transition = None;
+            }
+            return left;
+        }
+        let Some(index) = self.panel else {
+            return false;
+        };
+        let panel = &self.cutscene.panels[index];
+        if skip {
+            self.transition = Some(ActiveTransition::new(self.in_out, None));
+        } else if self.time >= panel.duration && (!panel.wait_for_input || advance) {
+            let next = self.cutscene.panels.get(index + 1);
+            self.transition = match next {
+                None => Some(ActiveTransition::new(self.in_out, None)),
+                Some(next) if next.screen != panel.screen => Some(ActiveTransition::new(
+                    self.cutscene.panel_transition,
+                    Some(index + 1),
+                )),
+                // same picture, so just swap the caption
+                Some(_) => {
+                    self.panel = Some(index + 1);
+                    self.time = 0.0;
+                    None
+                }
+            };
+        }
+        false
+    }
+    /// Whether it has been left and the transition back out is over
+    pub fn is_finished(&self) -> bool {
+        self.panel.is_none() && self.transition.is_none()
+    }
+    pub fn current_panel(&self) -> Option<&'static Panel> {
+        self.panel.map(|f| &self.cutscene.panels[f])
+    }
+    /// Draws the current panel's screen into the frame
+    pub fn draw_screen(&self, ctx: &mut DrawCtx) {
+        let Some(panel) = self.current_panel() else {
+            return;
+        };
+        ctx.draw_rectangle(0.0, 0.0, SCREEN_WIDTH, SCREEN_HEIGHT, BLACK);
+        if let Some(screen) = panel.screen {
+            let screen = ctx.sprites.screen(screen);
+            let progress = if panel.duration > 0.0 {
+                self.time / panel.duration
+            } else {
+                1.0
+            };
+            let (zoom, offset) = panel.ken_burns.at(progress);
+            let size = screen.get_at_time(0).size * SCREEN_SCALE * zoom;
+            let pos = (vec2(SCREEN_WIDTH, SCREEN_HEIGHT) - size) / 2.0 + offset;
+            ctx.draw_region(
+                screen.get_at_time((self.time * 1000.0) as u32).region,
+                pos.x.floor(),
+                pos.y.floor(),
+                WHITE,
+                size,
+            );
+        }
+    }
+    pub fn draw_transition(&self) {
+        if let Some(transition) = &self.transition {
+            transition.draw();
+        }
+    }
+    /// Draws the current panel's caption, once nothing is moving
+    pub fn draw_caption(&self, ctx: &mut DrawCtx) {
+        let Some(panel) = self.current_panel() else {
+            return;
+        };
+        let Some(caption) = &panel.caption else {
+            return;
+        };
+        if self.transition.is_some() {
+            return;
+        }
+        if panel.screen.is_some() {
+            let advance = ctx.input.take_advance();
+            draw_dialogue(caption.text, caption.speaker, advance, ctx);
+        } else {
+            draw_title_card(caption.text, ctx);
+        }
+    }
+}
+
+/// Text in the middle of a black screen
+fn draw_title_card(text: &str, ctx: &mut DrawCtx) {
+    let font_size = (16.0 * ctx.scale_factor) as u16;
+    let size = ctx.measure_text(text, font_size);
+    ctx.draw_text(
+        text,
+        ((ctx.screen_size.x - size.width) / 2.0).floor(),
+        ((ctx.screen_size.y + size.offset_y) / 2.0).floor(),
+        font_size,
+    );
+}
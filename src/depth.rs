@@ -0,0 +1,104 @@
+use macroquad::prelude::*;
+
+use crate::{assets::Map, characters::Character, utils::TILE_ANCHOR};
+
+/// Puts foreground tiles just in front of anything standing on the same row, so a wall's top edge
+/// still covers the feet of whoever stands right behind it
+const TILE_DEPTH_BIAS: f32 = 0.5;
+
+/// Anything drawn between the floor and the lighting
+#[derive(Clone, Copy)]
+pub enum Drawable<'b, 'a> {
+    Player,
+    Character(&'b Character<'a>),
+    /// A wall or detail tile, as (x, y, tile id)
+    Tile(usize, usize, u8),
+}
+
+/// World y something is sorted by, which is where its feet meet the floor.
+/// Tiles use the bottom edge of their tile.
+pub fn tile_depth(y: usize) -> f32 {
+    (y + 1) as f32 * 16.0 + TILE_DEPTH_BIAS
+}
+
+/// The player, characters and foreground tiles, ordered back to front.
+/// Things at the same depth keep the order they're listed in here.
+pub fn depth_sorted<'b, 'a>(
+    player_pos: Vec2,
+    characters: &'b [Character<'a>],
+    map: &Map,
+) -> Vec<Drawable<'b, 'a>> {
+    let mut drawables: Vec<(f32, Drawable)> =
+        vec![(player_pos.y + TILE_ANCHOR.y, Drawable::Player)];
+    drawables.extend(
+        characters
+            .iter()
+            .rev()
+            .map(|f| (f.depth(), Drawable::Character(f))),
+    );
+    drawables.extend(
+        map.foreground_tiles()
+            .map(|(x, y, tile)| (tile_depth(y), Drawable::Tile(x, y, tile))),
+    );
+    drawables.sort_by(|a, b| a.0.total_cmp(&b.0));
+    drawables.into_iter().map(|(_, f)| f).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assets::TileMap, characters::BASE_CHARACTER};
+
+    /// What's drawn, back to front, with the player at `player_y`
+    fn order(player_y: f32, characters: &[Character], map: &Map) -> Vec<&'static str> {
+        depth_sorted(vec2(16.0, player_y), characters, map)
+            .into_iter()
+            .map(|f| match f {
+                Drawable::Player => "player",
+                Drawable::Character(character) => character.name,
+                Drawable::Tile(..) => "wall",
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sorted_by_feet() {
+        // a wall on row 2, so its bottom edge is at y 48
+        let map = Map::headless(TileMap::from_rows(&["...", "...", ".#.", "..."]));
+        let characters = [
+            Character {
+                name: "guest",
+                draw_pos: vec2(0.0, 16.0),
+                ..BASE_CHARACTER
+            },
+            // stands a row behind the wall, but its sprite reaches down past it
+            Character {
+                name: "tall",
+                draw_pos: vec2(32.0, 16.0),
+                depth_bias: 32.0,
+                ..BASE_CHARACTER
+            },
+        ];
+        assert_eq!(
+            order(0.0, &characters, &map),
+            ["player", "guest", "wall", "tall"]
+        );
+        assert_eq!(
+            order(24.0, &characters, &map),
+            ["guest", "player", "wall", "tall"]
+        );
+        // feet on the wall's own row stay behind its top
+        assert_eq!(
+            order(32.0, &characters, &map),
+            ["guest", "player", "wall", "tall"]
+        );
+        assert_eq!(
+            order(40.0, &characters, &map),
+            ["guest", "wall", "player", "tall"]
+        );
+        assert_eq!(
+            order(64.0, &characters, &map),
+            ["guest", "wall", "tall", "player"]
+        );
+    }
+}
@@ -0,0 +1,174 @@
+use macroquad::{miniquad::window::screen_size, prelude::*};
+
+use crate::utils::{SCREEN_HEIGHT, SCREEN_WIDTH, create_camera};
+
+/// How the low resolution frame is scaled up to the window
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ScaleMode {
+    /// Only whole multiples, so every pixel is the same size
+    Integer,
+    /// As big as fits while keeping the aspect ratio
+    Fit,
+    /// Fills the whole window, aspect ratio be damned
+    Stretch,
+}
+impl ScaleMode {
+    pub const ALL: [ScaleMode; 3] = [ScaleMode::Integer, ScaleMode::Fit, ScaleMode::Stretch];
+    pub fn name(self) -> &'static str {
+        match self {
+            ScaleMode::Integer => "integer",
+            ScaleMode::Fit => "fit",
+            ScaleMode::Stretch => "stretch",
+        }
+    }
+    pub fn next(self) -> Self {
+        match self {
+            ScaleMode::Integer => ScaleMode::Fit,
+            ScaleMode::Fit => ScaleMode::Stretch,
+            ScaleMode::Stretch => ScaleMode::Integer,
+        }
+    }
+}
+
+/// Where dialogue and tooltips are drawn
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum UiResolution {
+    /// Into the low resolution frame with the world, scaled up with it
+    Native,
+    /// Straight onto the window over the scaled frame, for sharp text
+    Screen,
+}
+impl UiResolution {
+    pub const ALL: [UiResolution; 2] = [UiResolution::Native, UiResolution::Screen];
+    pub fn name(self) -> &'static str {
+        match self {
+            UiResolution::Native => "native",
+            UiResolution::Screen => "screen",
+        }
+    }
+    pub fn next(self) -> Self {
+        match self {
+            UiResolution::Native => UiResolution::Screen,
+            UiResolution::Screen => UiResolution::Native,
+        }
+    }
+}
+
+/// Where the frame ends up on a window of size `screen`. Anything outside of it is letterboxed.
+pub fn output_rect(mode: ScaleMode, screen: Vec2) -> Rect {
+    let fit = (screen.x / SCREEN_WIDTH).min(screen.y / SCREEN_HEIGHT);
+    let size = match mode {
+        // windows smaller than the frame still get something, even if it's squashed
+        ScaleMode::Integer if fit >= 1.0 => vec2(SCREEN_WIDTH, SCREEN_HEIGHT) * fit.floor(),
+        ScaleMode::Integer | ScaleMode::Fit => vec2(SCREEN_WIDTH, SCREEN_HEIGHT) * fit,
+        ScaleMode::Stretch => screen,
+    };
+    let pos = ((screen - size) / 2.0).floor();
+    Rect::new(pos.x, pos.y, size.x, size.y)
+}
+
+/// Pixel of the frame under `point` on a window of size `screen`, or None if it's in the letterboxing
+pub fn window_to_frame(mode: ScaleMode, screen: Vec2, point: Vec2) -> Option<Vec2> {
+    let rect = output_rect(mode, screen);
+    rect.contains(point)
+        .then(|| (point - rect.point()) / rect.size() * vec2(SCREEN_WIDTH, SCREEN_HEIGHT))
+}
+
+/// Renders the game at `SCREEN_WIDTH`×`SCREEN_HEIGHT` and scales it up to the window
+pub struct Display {
+    camera: Camera2D,
+    pub scale_mode: ScaleMode,
+    pub ui_resolution: UiResolution,
+}
+impl Display {
+    pub fn new() -> Self {
+        let mut camera = create_camera(SCREEN_WIDTH, SCREEN_HEIGHT);
+        camera.target = vec2(SCREEN_WIDTH, SCREEN_HEIGHT) / 2.0;
+        Self {
+            camera,
+            scale_mode: ScaleMode::Fit,
+            ui_resolution: UiResolution::Screen,
+        }
+    }
+    /// Starts drawing into the frame, in its own pixels
+    pub fn begin(&self) {
+        set_camera(&self.camera);
+        clear_background(BLACK);
+    }
+    /// Zooms everything drawn into the frame from now on about its middle
+    pub fn set_zoom(&self, zoom: f32) {
+        set_camera(&Camera2D {
+            zoom: self.camera.zoom * zoom,
+            target: self.camera.target,
+            render_target: self.camera.render_target.clone(),
+            ..Default::default()
+        });
+    }
+    /// Scales the frame onto the window. Drawing after this goes straight to the window.
+    pub fn present(&self) {
+        set_default_camera();
+        clear_background(BLACK);
+        let rect = output_rect(self.scale_mode, screen_size().into());
+        draw_texture_ex(
+            &self.camera.render_target.as_ref().unwrap().texture,
+            rect.x,
+            rect.y,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(rect.size()),
+                ..Default::default()
+            },
+        );
+    }
+    /// Sets up drawing UI over the presented frame at window resolution.
+    /// Returns the size of the area to draw in and its scale compared to the frame.
+    pub fn begin_screen_ui(&self) -> (Vec2, f32) {
+        let screen: Vec2 = screen_size().into();
+        let rect = output_rect(self.scale_mode, screen);
+        set_camera(&Camera2D {
+            zoom: vec2(2.0 / rect.w, -2.0 / rect.h),
+            target: rect.size() / 2.0,
+            // viewports count from the bottom of the window
+            viewport: Some((
+                rect.x as i32,
+                (screen.y - rect.y - rect.h) as i32,
+                rect.w as i32,
+                rect.h as i32,
+            )),
+            ..Default::default()
+        });
+        let scale_factor = (rect.w / SCREEN_WIDTH).min(rect.h / SCREEN_HEIGHT);
+        (rect.size(), scale_factor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fit_letterboxes_the_top_and_bottom() {
+        // twice the frame across, with 112 pixels spare above and below
+        let screen = vec2(1024.0, 800.0);
+        let frame = |point| window_to_frame(ScaleMode::Fit, screen, point);
+        assert_eq!(frame(vec2(0.0, 112.0)), Some(Vec2::ZERO));
+        assert_eq!(frame(vec2(512.0, 400.0)), Some(vec2(256.0, 144.0)));
+        assert_eq!(frame(vec2(512.0, 50.0)), None);
+        assert_eq!(frame(vec2(512.0, 750.0)), None);
+    }
+
+    #[test]
+    fn integer_rounds_down_and_centres() {
+        // fits 2.43 times over, so it's drawn twice the size in the middle
+        let screen = vec2(1300.0, 700.0);
+        assert_eq!(
+            output_rect(ScaleMode::Integer, screen),
+            Rect::new(138.0, 62.0, 1024.0, 576.0)
+        );
+        let frame = |point| window_to_frame(ScaleMode::Integer, screen, point);
+        assert_eq!(frame(vec2(138.0, 62.0)), Some(Vec2::ZERO));
+        assert_eq!(frame(vec2(1160.0, 636.0)), Some(vec2(511.0, 287.0)));
+        assert_eq!(frame(vec2(100.0, 300.0)), None);
+        assert_eq!(frame(vec2(1200.0, 300.0)), None);
+    }
+}
@@ -0,0 +1,257 @@
+use std::collections::HashSet;
+
+use macroquad::prelude::*;
+
+use crate::assets::{Map, TileMap};
+
+/// How far the player can see, in tiles
+pub const VIEW_RADIUS: usize = 12;
+/// Fog left over places the player has seen before but can't see right now
+pub const REMEMBERED_FOG: f32 = 0.6;
+/// How quickly fog fades in and out, in full fades per second
+const FADE_SPEED: f32 = 4.0;
+
+// (xx, xy, yx, yy) multipliers that map the first octant onto each of the eight
+const OCTANTS: [(isize, isize, isize, isize); 8] = [
+    (1, 0, 0, 1),
+    (0, 1, 1, 0),
+    (0, -1, 1, 0),
+    (-1, 0, 0, 1),
+    (-1, 0, 0, -1),
+    (0, -1, -1, 0),
+    (0, 1, -1, 0),
+    (1, 0, 0, -1),
+];
+
+fn blocks_sight(walls: &TileMap, x: usize, y: usize) -> bool {
+    x >= walls.1 || walls.0.get(x + y * walls.1).is_none_or(|f| *f != 0)
+}
+
+/// Every tile visible from `origin` within `radius`, found by shadowcasting over the walls layer.
+/// Walls themselves are visible, but nothing behind them is, and nothing off the map.
+pub fn compute_visible(
+    walls: &TileMap,
+    origin: (usize, usize),
+    radius: usize,
+) -> HashSet<(usize, usize)> {
+    let mut visible = HashSet::new();
+    visible.insert(origin);
+    for transform in OCTANTS {
+        cast_light(walls, origin, radius, 1, 1.0, 0.0, transform, &mut visible);
+    }
+    visible
+}
+
+#[allow(clippy::too_many_arguments)]
+fn cast_light(
+    walls: &TileMap,
+    origin: (usize, usize),
+    radius: usize,
+    row: usize,
+    mut start: f32,
+    end: f32,
+    (xx, xy, yx, yy): (isize, isize, isize, isize),
+    visible: &mut HashSet<(usize, usize)>,
+) {
+    if start < end {
+        return;
+    }
+    let radius_squared = (radius * radius) as isize;
+    let mut new_start = 0.0;
+    for distance in row..=radius {
+        let dy = -(distance as isize);
+        let mut blocked = false;
+        for dx in -(distance as isize)..=0 {
+            let left_slope = (dx as f32 - 0.5) / (dy as f32 + 0.5);
+            let right_slope = (dx as f32 + 0.5) / (dy as f32 - 0.5);
+            if start < right_slope {
+                continue;
+            } else if end > left_slope {
+                break;
+            }
+            let x = origin.0 as isize + dx * xx + dy * xy;
+            let y = origin.1 as isize + dx * yx + dy * yy;
+            // outside the map counts as solid
+            let opaque = x < 0 || y < 0 || blocks_sight(walls, x as usize, y as usize);
+            let on_map = x >= 0
+                && y >= 0
+                && (x as usize) < walls.1
+                && (y as usize) < walls.0.len() / walls.1;
+            if on_map && dx * dx + dy * dy <= radius_squared {
+                visible.insert((x as usize, y as usize));
+            }
+            if blocked {
+                if opaque {
+                    new_start = right_slope;
+                } else {
+                    blocked = false;
+                    start = new_start;
+                }
+            } else if opaque && distance < radius {
+                blocked = true;
+                cast_light(
+                    walls,
+                    origin,
+                    radius,
+                    distance + 1,
+                    start,
+                    left_slope,
+                    (xx, xy, yx, yy),
+                    visible,
+                );
+                new_start = right_slope;
+            }
+        }
+        if blocked {
+            break;
+        }
+    }
+}
+
+/// What the player can see, what they remember, and how foggy each tile currently is
+pub struct Fov {
+    pub width: usize,
+    pub height: usize,
+    pub visible: HashSet<(usize, usize)>,
+    pub seen: HashSet<(usize, usize)>,
+    /// Tiles that get fogged at all. Outdoors, with neither floor nor walls, always stays clear.
+    indoors: Vec<bool>,
+    /// Current fog of every tile, from 0 (clear) to 1 (never seen)
+    pub fog: Vec<f32>,
+}
+impl Fov {
+    pub fn new(map: &Map) -> Self {
+        let width = map.walls.1;
+        let height = map.walls.0.len() / width;
+        let indoors: Vec<bool> = (0..width * height)
+            .map(|index| map.floor.0[index] != 0 || map.walls.0[index] != 0)
+            .collect();
+        Self {
+            width,
+            height,
+            visible: HashSet::new(),
+            seen: HashSet::new(),
+            fog: indoors.iter().map(|f| if *f { 1.0 } else { 0.0 }).collect(),
+            indoors,
+        }
+    }
+    pub fn update(&mut self, walls: &TileMap, origin: (usize, usize), delta_time: f32) {
+        self.visible = compute_visible(walls, origin, VIEW_RADIUS);
+        self.seen.extend(self.visible.iter().copied());
+        for (index, fog) in self.fog.iter_mut().enumerate() {
+            let pos = (index % self.width, index / self.width);
+            let target = if !self.indoors[index] || self.visible.contains(&pos) {
+                0.0
+            } else if self.seen.contains(&pos) {
+                REMEMBERED_FOG
+            } else {
+                1.0
+            };
+            *fog = move_towards(*fog, target, delta_time * FADE_SPEED);
+        }
+    }
+}
+
+fn move_towards(value: f32, target: f32, amount: f32) -> f32 {
+    if value < target {
+        (value + amount).min(target)
+    } else {
+        (value - amount).max(target)
+    }
+}
+
+/// Draws the fog of a `Fov` over the map, blurred between tiles
+pub struct Fog {
+    texture: Texture2D,
+    image: Image,
+}
+impl Fog {
+    pub fn new(fov: &Fov) -> Self {
+        let image = Image::gen_image_color(fov.width as u16, fov.height as u16, BLACK);
+        let texture = Texture2D::from_image(&image);
+        texture.set_filter(FilterMode::Linear);
+        Self { texture, image }
+    }
+    pub fn draw(&mut self, fov: &Fov, x: f32, y: f32, dest_size: Vec2) {
+        for (index, fog) in fov.fog.iter().enumerate() {
+            self.image.set_pixel(
+                (index % fov.width) as u32,
+                (index / fov.width) as u32,
+                BLACK.with_alpha(*fog),
+            );
+        }
+        self.texture.update(&self.image);
+        draw_texture_ex(
+            &self.texture,
+            x,
+            y,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(dest_size),
+                ..Default::default()
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Walls from rows of text, `#` for a wall and anything else for floor
+    fn walls(rows: &[&str]) -> TileMap {
+        let tiles = rows
+            .iter()
+            .flat_map(|row| row.chars().map(|f| (f == '#') as u8))
+            .collect();
+        TileMap(tiles, rows[0].len())
+    }
+
+    #[test]
+    fn open_room_is_all_visible() {
+        let walls = walls(&[".......", ".......", ".......", ".......", "......."]);
+        let visible = compute_visible(&walls, (3, 2), VIEW_RADIUS);
+        assert_eq!(visible.len(), 7 * 5);
+    }
+
+    #[test]
+    fn wall_hides_what_is_behind_it() {
+        let walls = walls(&[
+            ".........",
+            ".........",
+            "....#....",
+            ".........",
+            ".........",
+        ]);
+        let visible = compute_visible(&walls, (1, 2), VIEW_RADIUS);
+        assert!(visible.contains(&(4, 2)));
+        assert!(!visible.contains(&(6, 2)));
+        assert!(!visible.contains(&(8, 2)));
+        assert!(visible.contains(&(8, 0)));
+    }
+
+    #[test]
+    fn corridor_sees_along_but_not_through() {
+        let walls = walls(&[
+            "#########",
+            "#.......#",
+            "#########",
+            "#.......#",
+            "#########",
+        ]);
+        let visible = compute_visible(&walls, (1, 1), VIEW_RADIUS);
+        assert!((0..9).all(|x| visible.contains(&(x, 1))));
+        assert!((1..8).all(|x| visible.contains(&(x, 2))));
+        assert!((0..9).all(|x| !visible.contains(&(x, 3))));
+    }
+
+    #[test]
+    fn edges_stay_on_the_map() {
+        let walls = walls(&["....", "....", "...."]);
+        for origin in [(0, 0), (3, 0), (0, 2), (3, 2)] {
+            let visible = compute_visible(&walls, origin, VIEW_RADIUS);
+            assert_eq!(visible.len(), 4 * 3);
+            assert!(visible.iter().all(|(x, y)| *x < 4 && *y < 3));
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use macroquad::prelude::*;
+
+/// Something the player can do with a key, bound to any number of keys
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum InputAction {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Interact,
+    Pause,
+    /// Skips cutscenes
+    Skip,
+}
+impl InputAction {
+    pub const ALL: [InputAction; 7] = [
+        InputAction::MoveUp,
+        InputAction::MoveDown,
+        InputAction::MoveLeft,
+        InputAction::MoveRight,
+        InputAction::Interact,
+        InputAction::Pause,
+        InputAction::Skip,
+    ];
+    /// Name in the settings file
+    pub fn name(self) -> &'static str {
+        match self {
+            InputAction::MoveUp => "move_up",
+            InputAction::MoveDown => "move_down",
+            InputAction::MoveLeft => "move_left",
+            InputAction::MoveRight => "move_right",
+            InputAction::Interact => "interact",
+            InputAction::Pause => "pause",
+            InputAction::Skip => "skip",
+        }
+    }
+    /// Escape both pauses and skips, which is what players reach for either way. When a
+    /// cutscene is playing skipping wins, see `skipping` in main.
+    pub fn default_keys(self) -> &'static [KeyCode] {
+        match self {
+            InputAction::MoveUp => &[KeyCode::W, KeyCode::Up],
+            InputAction::MoveDown => &[KeyCode::S, KeyCode::Down],
+            InputAction::MoveLeft => &[KeyCode::A, KeyCode::Left],
+            InputAction::MoveRight => &[KeyCode::D, KeyCode::Right],
+            InputAction::Interact => &[KeyCode::E],
+            InputAction::Pause => &[KeyCode::Escape, KeyCode::P],
+            InputAction::Skip => &[KeyCode::Escape],
+        }
+    }
+}
+
+/// The keyboard for one frame, read through the player's key bindings.
+/// Whatever uses a press takes it, so the same press can't set off two things.
+pub struct Input {
+    pub bindings: Vec<(InputAction, KeyCode)>,
+    /// Actions pressed this frame that nothing has taken yet
+    pressed: Vec<InputAction>,
+    /// Window position of a click or tap this frame, if nothing has taken it yet
+    click: Option<Vec2>,
+}
+impl Input {
+    pub fn new(bindings: &[(InputAction, KeyCode)]) -> Self {
+        let mut input = Self::idle(bindings);
+        // taps come through as clicks too
+        input.click = is_mouse_button_pressed(MouseButton::Left).then(|| mouse_position().into());
+        input.pressed = InputAction::ALL
+            .into_iter()
+            .filter(|action| input.keys(*action).any(is_key_pressed))
+            .collect();
+        input
+    }
+    /// Nothing pressed or clicked, without reading the keyboard
+    pub fn idle(bindings: &[(InputAction, KeyCode)]) -> Self {
+        Self {
+            bindings: bindings.to_vec(),
+            pressed: Vec::new(),
+            click: None,
+        }
+    }
+    /// Every key bound to `action`
+    pub fn keys(&self, action: InputAction) -> impl Iterator<Item = KeyCode> + '_ {
+        self.bindings
+            .iter()
+            .filter(move |(bound, _)| *bound == action)
+            .map(|(_, key)| *key)
+    }
+    /// Whether any key bound to `action` was pressed this frame, and nothing has taken the press yet
+    pub fn is_pressed(&self, action: InputAction) -> bool {
+        self.pressed.contains(&action)
+    }
+    /// Takes this frame's press of `action`, if nothing else has. Returns whether there was one.
+    pub fn take(&mut self, action: InputAction) -> bool {
+        let pressed = self.is_pressed(action);
+        self.pressed.retain(|f| *f != action);
+        pressed
+    }
+    /// Acts as if `action` was pressed this frame, for doing things on the player's behalf
+    pub fn press(&mut self, action: InputAction) {
+        if !self.pressed.contains(&action) {
+            self.pressed.push(action);
+        }
+    }
+    /// Takes this frame's click, if nothing else has
+    pub fn take_click(&mut self) -> Option<Vec2> {
+        self.click.take()
+    }
+    /// Takes a press of interact or a click, for advancing dialogue with either
+    pub fn take_advance(&mut self) -> bool {
+        self.take(InputAction::Interact) || self.take_click().is_some()
+    }
+    pub fn is_down(&self, action: InputAction) -> bool {
+        self.keys(action).any(is_key_down)
+    }
+    /// Name of the first key bound to `action`, for prompts
+    pub fn key_name(&self, action: InputAction) -> &'static str {
+        self.keys(action).next().map_or("?", key_name)
+    }
+    /// Direction the movement keys held down point in, with each axis from -1 to 1
+    pub fn move_axis(&self) -> Vec2 {
+        let axis = |negative, positive| {
+            self.is_down(positive) as i32 as f32 - self.is_down(negative) as i32 as f32
+        };
+        vec2(
+            axis(InputAction::MoveLeft, InputAction::MoveRight),
+            axis(InputAction::MoveUp, InputAction::MoveDown),
+        )
+    }
+}
+
+/// Every action with its default keys
+pub fn default_bindings() -> Vec<(InputAction, KeyCode)> {
+    InputAction::ALL
+        .iter()
+        .flat_map(|action| action.default_keys().iter().map(|key| (*action, *key)))
+        .collect()
+}
+
+/// Keys that can be bound, and what they're called on screen and in the settings file
+const KEY_NAMES: &[(KeyCode, &str)] = &[
+    (KeyCode::A, "A"),
+    (KeyCode::B, "B"),
+    (KeyCode::C, "C"),
+    (KeyCode::D, "D"),
+    (KeyCode::E, "E"),
+    (KeyCode::F, "F"),
+    (KeyCode::G, "G"),
+    (KeyCode::H, "H"),
+    (KeyCode::I, "I"),
+    (KeyCode::J, "J"),
+    (KeyCode::K, "K"),
+    (KeyCode::L, "L"),
+    (KeyCode::M, "M"),
+    (KeyCode::N, "N"),
+    (KeyCode::O, "O"),
+    (KeyCode::P, "P"),
+    (KeyCode::Q, "Q"),
+    (KeyCode::R, "R"),
+    (KeyCode::S, "S"),
+    (KeyCode::T, "T"),
+    (KeyCode::U, "U"),
+    (KeyCode::V, "V"),
+    (KeyCode::W, "W"),
+    (KeyCode::X, "X"),
+    (KeyCode::Y, "Y"),
+    (KeyCode::Z, "Z"),
+    (KeyCode::Key0, "0"),
+    (KeyCode::Key1, "1"),
+    (KeyCode::Key2, "2"),
+    (KeyCode::Key3, "3"),
+    (KeyCode::Key4, "4"),
+    (KeyCode::Key5, "5"),
+    (KeyCode::Key6, "6"),
+    (KeyCode::Key7, "7"),
+    (KeyCode::Key8, "8"),
+    (KeyCode::Key9, "9"),
+    (KeyCode::Up, "Up"),
+    (KeyCode::Down, "Down"),
+    (KeyCode::Left, "Left"),
+    (KeyCode::Right, "Right"),
+    (KeyCode::Space, "Space"),
+    (KeyCode::Enter, "Enter"),
+    (KeyCode::Escape, "Esc"),
+    (KeyCode::Tab, "Tab"),
+    (KeyCode::Backspace, "Backspace"),
+    (KeyCode::LeftShift, "Shift"),
+    (KeyCode::LeftControl, "Ctrl"),
+];
+
+/// Short name of a key, for prompts and the settings file
+pub fn key_name(key: KeyCode) -> &'static str {
+    KEY_NAMES
+        .iter()
+        .find(|(code, _)| *code == key)
+        .map_or("?", |(_, name)| name)
+}
+
+/// The key with this name, if it's one that can be bound
+pub fn key_from_name(name: &str) -> Option<KeyCode> {
+    KEY_NAMES
+        .iter()
+        .find(|(_, key_name)| key_name.eq_ignore_ascii_case(name))
+        .map(|(code, _)| *code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_names_round_trip() {
+        for (key, name) in KEY_NAMES {
+            assert_eq!(key_name(*key), *name);
+            assert_eq!(key_from_name(name), Some(*key));
+            assert_eq!(key_from_name(&name.to_lowercase()), Some(*key));
+        }
+        assert_eq!(key_from_name("Banana"), None);
+    }
+
+    #[test]
+    fn every_action_has_a_default_key() {
+        let bindings = default_bindings();
+        for action in InputAction::ALL {
+            assert!(
+                bindings.iter().any(|(bound, _)| *bound == action),
+                "{action:?}"
+            );
+            // and every one of them can be written into the settings file
+            for key in action.default_keys() {
+                assert_ne!(key_name(*key), "?", "{action:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,318 @@
+use macroquad::prelude::*;
+use macroquad::{
+    material::{Material, MaterialParams, gl_use_default_material, gl_use_material},
+    miniquad::{BlendFactor, BlendState, BlendValue, Equation, PipelineParams},
+};
+
+use crate::assets::TileMap;
+
+/// Colour of the house where no light reaches it
+pub const AMBIENT: Color = Color::new(0.3, 0.3, 0.45, 1.0);
+/// How many light map cells fit along one side of a tile
+pub const CELLS_PER_TILE: usize = 2;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Light {
+    /// World position. Lights attached to a character are relative to its `draw_pos` instead.
+    pub pos: Vec2,
+    pub color: Color,
+    /// Distance in pixels where the light has faded out completely
+    pub radius: f32,
+    /// How much the brightness wavers, from 0 to 1
+    pub flicker: f32,
+}
+impl Light {
+    /// Brightness multiplier at the given time, wavering by up to `flicker`
+    pub fn flicker_at(&self, time: f32) -> f32 {
+        // offset by position so lights don't all flicker in sync
+        let phase = self.pos.x * 0.37 + self.pos.y * 0.11;
+        let wave = (time * 13.0 + phase).sin() * (time * 7.3 + phase * 2.0).sin();
+        1.0 - self.flicker * (0.5 + 0.5 * wave)
+    }
+}
+
+pub const FIRE_LIGHT: Light = Light {
+    pos: Vec2::new(16.0, 40.0),
+    color: Color::new(1.0, 0.6, 0.3, 1.0),
+    radius: 120.0,
+    flicker: 0.25,
+};
+pub const STOVE_LIGHT: Light = Light {
+    pos: Vec2::new(8.0, 8.0),
+    color: Color::new(0.9, 0.5, 0.25, 1.0),
+    radius: 40.0,
+    flicker: 0.1,
+};
+/// Cold glow of the snow outside, placed on the map's window markers
+pub const WINDOW_LIGHT: Light = Light {
+    pos: Vec2::new(0.0, 20.0),
+    color: Color::new(0.45, 0.55, 0.9, 1.0),
+    radius: 56.0,
+    flicker: 0.05,
+};
+
+/// Light reaching every cell of the map, `CELLS_PER_TILE` cells to a tile
+pub struct LightMap {
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<Color>,
+}
+impl LightMap {
+    pub fn get(&self, x: usize, y: usize) -> Color {
+        self.cells[x + y * self.width]
+    }
+}
+
+fn is_wall(walls: &TileMap, pos: Vec2) -> bool {
+    if pos.x < 0.0 || pos.y < 0.0 {
+        return false;
+    }
+    let (x, y) = ((pos.x / 16.0) as usize, (pos.y / 16.0) as usize);
+    x < walls.1 && walls.0.get(x + y * walls.1).is_some_and(|f| *f != 0)
+}
+
+/// Whether nothing on the walls layer stands between `from` and `to`.
+/// The tiles at both ends don't count, so lights inside walls and the faces of walls still work.
+pub fn line_of_sight(walls: &TileMap, from: Vec2, to: Vec2) -> bool {
+    let (start_tile, end_tile) = ((from / 16.0).floor(), (to / 16.0).floor());
+    let steps = (from.distance(to) / 4.0).ceil() as usize;
+    (1..steps).all(|i| {
+        let pos = from.lerp(to, i as f32 / steps as f32);
+        let tile = (pos / 16.0).floor();
+        tile == start_tile || tile == end_tile || !is_wall(walls, pos)
+    })
+}
+
+/// How much of a light at `pos` reaches every cell, from 0 to 1, before its colour and flicker
+fn light_coverage(walls: &TileMap, pos: Vec2, radius: f32) -> Vec<f32> {
+    let width = walls.1 * CELLS_PER_TILE;
+    let height = (walls.0.len() / walls.1) * CELLS_PER_TILE;
+    let cell_size = 16.0 / CELLS_PER_TILE as f32;
+    (0..width * height)
+        .map(|index| {
+            let center =
+                vec2((index % width) as f32 + 0.5, (index / width) as f32 + 0.5) * cell_size;
+            let distance = center.distance(pos);
+            if distance >= radius || !line_of_sight(walls, pos, center) {
+                0.0
+            } else {
+                (1.0 - distance / radius).powi(2)
+            }
+        })
+        .collect()
+}
+
+/// Remembers how far every light reaches, so walls are only traced again when a light
+/// moves or the walls change. Only colour and flicker are worked out every frame.
+#[derive(Default)]
+pub struct LightCache {
+    walls: Option<TileMap>,
+    /// Position and radius of each light, with its coverage of every cell
+    coverage: Vec<(Vec2, f32, Vec<f32>)>,
+}
+impl LightCache {
+    /// Adds up all lights over the map, each one blocked by the walls layer
+    pub fn light_map(&mut self, walls: &TileMap, lights: &[Light], time: f32) -> LightMap {
+        if self.walls.as_ref() != Some(walls) {
+            self.walls = Some(walls.clone());
+            self.coverage.clear();
+        }
+        let mut old = std::mem::take(&mut self.coverage);
+        for light in lights {
+            let cached = old
+                .iter()
+                .position(|(pos, radius, _)| *pos == light.pos && *radius == light.radius);
+            self.coverage.push(match cached {
+                Some(index) => old.swap_remove(index),
+                None => (
+                    light.pos,
+                    light.radius,
+                    light_coverage(walls, light.pos, light.radius),
+                ),
+            });
+        }
+
+        let width = walls.1 * CELLS_PER_TILE;
+        let height = (walls.0.len() / walls.1) * CELLS_PER_TILE;
+        let mut cells = vec![AMBIENT; width * height];
+        for (light, (_, _, coverage)) in lights.iter().zip(&self.coverage) {
+            let brightness = light.flicker_at(time);
+            for (cell, amount) in cells.iter_mut().zip(coverage) {
+                let amount = amount * brightness;
+                cell.r = (cell.r + light.color.r * amount).min(1.0);
+                cell.g = (cell.g + light.color.g * amount).min(1.0);
+                cell.b = (cell.b + light.color.b * amount).min(1.0);
+            }
+        }
+        LightMap {
+            width,
+            height,
+            cells,
+        }
+    }
+}
+
+const VERTEX_SHADER: &str = "#version 100
+attribute vec3 position;
+attribute vec2 texcoord;
+attribute vec4 color0;
+varying lowp vec2 uv;
+varying lowp vec4 color;
+uniform mat4 Model;
+uniform mat4 Projection;
+void main() {
+    gl_Position = Projection * Model * vec4(position, 1);
+    color = color0 / 255.0;
+    uv = texcoord;
+}";
+const FRAGMENT_SHADER: &str = "#version 100
+varying lowp vec4 color;
+varying lowp vec2 uv;
+uniform sampler2D Texture;
+void main() {
+    gl_FragColor = color * texture2D(Texture, uv);
+}";
+
+/// Draws a `LightMap` by multiplying it over whatever is already on screen
+pub struct Lighting {
+    texture: Texture2D,
+    image: Image,
+    material: Material,
+}
+impl Lighting {
+    pub fn new(walls: &TileMap) -> Self {
+        let width = (walls.1 * CELLS_PER_TILE) as u16;
+        let height = (walls.0.len() / walls.1 * CELLS_PER_TILE) as u16;
+        let image = Image::gen_image_color(width, height, WHITE);
+        let texture = Texture2D::from_image(&image);
+        texture.set_filter(FilterMode::Linear);
+        let material = load_material(
+            ShaderSource::Glsl {
+                vertex: VERTEX_SHADER,
+                fragment: FRAGMENT_SHADER,
+            },
+            MaterialParams {
+                pipeline_params: PipelineParams {
+                    color_blend: Some(BlendState::new(
+                        Equation::Add,
+                        BlendFactor::Value(BlendValue::DestinationColor),
+                        BlendFactor::Zero,
+                    )),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        Self {
+            texture,
+            image,
+            material,
+        }
+    }
+    pub fn draw(&mut self, light_map: &LightMap, x: f32, y: f32, dest_size: Vec2) {
+        for y in 0..light_map.height {
+            for x in 0..light_map.width {
+                self.image
+                    .set_pixel(x as u32, y as u32, light_map.get(x, y));
+            }
+        }
+        self.texture.update(&self.image);
+        gl_use_material(&self.material);
+        draw_texture_ex(
+            &self.texture,
+            x,
+            y,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(dest_size),
+                ..Default::default()
+            },
+        );
+        gl_use_default_material();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An 8x3 tile room split in two by a wall down column 4
+    fn room() -> TileMap {
+        let mut tiles = vec![0; 8 * 3];
+        for y in 0..3 {
+            tiles[4 + y * 8] = 1;
+        }
+        TileMap(tiles, 8)
+    }
+
+    fn light(pos: Vec2, color: Color, radius: f32) -> Light {
+        Light {
+            pos,
+            color,
+            radius,
+            flicker: 0.0,
+        }
+    }
+
+    /// Light map cell covering world position `pos`
+    fn cell_at(light_map: &LightMap, pos: Vec2) -> Color {
+        let cell = (pos / (16.0 / CELLS_PER_TILE as f32)).floor();
+        light_map.get(cell.x as usize, cell.y as usize)
+    }
+
+    #[test]
+    fn walls_block_light() {
+        let walls = room();
+        let lights = [light(vec2(24.0, 24.0), WHITE, 200.0)];
+        let light_map = LightCache::default().light_map(&walls, &lights, 0.0);
+        assert!(cell_at(&light_map, vec2(52.0, 24.0)).r > AMBIENT.r);
+        assert_eq!(cell_at(&light_map, vec2(100.0, 24.0)), AMBIENT);
+    }
+
+    #[test]
+    fn fades_out_by_radius() {
+        let walls = TileMap(vec![0; 8 * 3], 8);
+        let lights = [light(
+            vec2(4.0, 24.0),
+            Color::new(0.5, 0.5, 0.5, 1.0),
+            100.0,
+        )];
+        let light_map = LightCache::default().light_map(&walls, &lights, 0.0);
+        let row: Vec<f32> = (0..light_map.width)
+            .map(|x| light_map.get(x, 3).r)
+            .collect();
+        assert!(row.windows(2).all(|f| f[0] >= f[1]));
+        assert!(row[0] > row[5]);
+        // the last cells are past the radius
+        assert_eq!(row[15], AMBIENT.r);
+    }
+
+    #[test]
+    fn overlapping_lights_add_up_to_full() {
+        let walls = TileMap(vec![0; 8 * 3], 8);
+        let pos = vec2(64.0, 24.0);
+        let dim = light(pos, Color::new(0.1, 0.1, 0.1, 1.0), 100.0);
+        let mut cache = LightCache::default();
+        let one = cell_at(&cache.light_map(&walls, &[dim], 0.0), pos + 8.0);
+        let two = cell_at(&cache.light_map(&walls, &[dim, dim], 0.0), pos + 8.0);
+        assert!((two.r - AMBIENT.r - 2.0 * (one.r - AMBIENT.r)).abs() < 1e-5);
+
+        let bright = light(pos, WHITE, 100.0);
+        let both = cell_at(&cache.light_map(&walls, &[bright, bright], 0.0), pos);
+        assert_eq!((both.r, both.g, both.b), (1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn cache_follows_moving_lights() {
+        let walls = room();
+        let mut cache = LightCache::default();
+        let mut lamp = light(vec2(24.0, 24.0), WHITE, 60.0);
+        cache.light_map(&walls, &[lamp], 0.0);
+        lamp.pos = vec2(100.0, 24.0);
+        let moved = cache.light_map(&walls, &[lamp], 0.0);
+        let fresh = LightCache::default().light_map(&walls, &[lamp], 0.0);
+        assert_eq!(moved.cells, fresh.cells);
+        assert_eq!(cache.coverage.len(), 1);
+    }
+}
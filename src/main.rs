@@ -1,181 +1,364 @@
-use macroquad::{miniquad::window::screen_size, prelude::*};
+use macroquad::prelude::*;
 
-use crate::assets::Assets;
+use crate::assets::{Assets, Map, PlaybackMode};
+use crate::audio::{Audio, AudioBackend};
+use crate::camera::{Camera, CameraTarget};
 use crate::characters::*;
+use crate::cutscene::{CutsceneEnd, CutscenePlayer};
+use crate::depth::{Drawable, depth_sorted};
+use crate::display::{Display, UiResolution, window_to_frame};
+use crate::fov::{Fog, Fov};
+use crate::input::{Input, InputAction};
+use crate::lighting::{Light, LightCache, Lighting, WINDOW_LIGHT};
+use crate::manifest::START_MAP;
+use crate::menu::{Menu, MenuEvent, MenuInput, Page, TitleBackdrop};
 use crate::player::{Direction, MOVE_TIME, Player};
+use crate::render::{DrawCommand, MacroquadRenderer, TextureId};
+use crate::settings::{Settings, settings_store};
+use crate::soundscape::Soundscape;
+use crate::transitions::{ActiveTransition, FADE};
 use crate::utils::*;
+use crate::voice::Typewriter;
+use crate::weather::{ParticleKind, Weather, draw_settled_snow, settle_snow};
 
 mod assets;
+mod atlas;
+mod audio;
+mod camera;
 mod characters;
+mod cutscene;
+mod depth;
+mod display;
+mod fov;
+mod input;
+mod lighting;
+mod manifest;
+mod menu;
 mod player;
+mod render;
+mod rng;
+mod settings;
+mod soundscape;
+#[cfg_attr(not(feature = "audio"), allow(dead_code))]
+mod synth;
+mod transitions;
 mod utils;
+mod voice;
+mod weather;
+
+/// How bad the storm is when the night starts
+const START_STORM_INTENSITY: f32 = 0.6;
 
 struct Game<'a> {
     assets: &'a Assets,
+    map: &'a Map,
     player: Player<'a>,
     time: f32,
     characters: Vec<Character<'a>>,
-    screen: Option<(usize, f32)>,
+    cutscene: Option<CutscenePlayer>,
+    /// Map to switch to once the transition covers the screen. Setting it is how the game changes maps,
+    /// with whichever transition suits.
+    map_change: Option<ActiveTransition<&'static str>>,
+    /// Set once the game is over and should start again from the beginning
+    restart: bool,
+    lighting: Lighting,
+    light_cache: LightCache,
+    window_lights: Vec<Light>,
+    fov: Fov,
+    fog: Fog,
+    weather: Weather,
+    display: Display,
+    camera: Camera,
+    audio: Audio<'a>,
+    soundscape: Soundscape,
+    typewriter: Typewriter,
+    /// Character whose prompt is showing, the only one that can be interacted with this frame
+    interaction_target: Option<usize>,
+    /// Character clicked on, to interact with once the player has walked over
+    click_target: Option<usize>,
 }
 impl<'a> Game<'a> {
-    fn new(assets: &'a Assets) -> Self {
+    fn new(assets: &'a Assets, audio: &'a dyn AudioBackend) -> Self {
+        let map = assets.map(START_MAP);
+        let fov = Fov::new(map);
+        let player = Player::new(map.special.find_tile(0));
         Self {
             assets,
-            player: Player::new(assets.map.special.find_tile(0)),
+            map,
+            camera: Camera::new(player.draw_pos),
+            player,
             time: 0.0,
             characters: vec![
-                stove(assets.map.special.find_tile(7)),
-                fireplace(assets.map.special.find_tile(3), assets),
-                door(assets.map.special.find_tile(2), assets),
-                raincoat_ferret(assets.map.special.find_tile(1), assets),
-                child_ferret(assets, 0),
-                child_ferret(assets, 1),
-                mother_ferret(assets),
-                //test_character(assets.map.special.find_tile(4), assets),
+                stove(map.special.find_tile(7)),
+                fireplace(map.special.find_tile(3), assets),
+                door(map.special.find_tile(2), assets),
+                raincoat_ferret(map.special.find_tile(1), assets, map),
+                child_ferret(assets, map, 0),
+                child_ferret(assets, map, 1),
+                mother_ferret(assets, map),
+                //test_character(map.special.find_tile(4), assets, map),
             ],
-            screen: None,
+            cutscene: None,
+            // the night fades in from black
+            map_change: Some(ActiveTransition::covered(FADE, START_MAP)),
+            restart: false,
+            lighting: Lighting::new(&map.walls),
+            light_cache: LightCache::default(),
+            window_lights: window_lights(map),
+            fog: Fog::new(&fov),
+            fov,
+            display: Display::new(),
+            weather: Weather::new(miniquad::date::now() as u64, START_STORM_INTENSITY),
+            audio: Audio::new(audio),
+            soundscape: Soundscape::new(map),
+            typewriter: Typewriter::default(),
+            interaction_target: None,
+            click_target: None,
         }
     }
-    fn update(&mut self) {
-        set_default_camera();
-        clear_background(BLACK);
-        let (screen_width, screen_height) = screen_size();
-        let scale_factor = (screen_width / SCREEN_WIDTH).min(screen_height / SCREEN_HEIGHT);
-        let mut delta_time = get_frame_time();
-        #[cfg(debug_assertions)]
-        {
-            if is_key_down(KeyCode::LeftShift) {
-                delta_time *= 3.0;
+    /// Switches to the map called `name`, with the player on its starting tile.
+    /// Characters stay where they are, so maps sharing them should line up.
+    fn enter_map(&mut self, name: &str) {
+        let map = self.assets.map(name);
+        self.map = map;
+        let (x, y) = map.special.find_tile(0);
+        (self.player.x, self.player.y) = (x, y);
+        self.player.draw_pos = vec2(x as f32, y as f32) * 16.0;
+        self.player.path.clear();
+        self.click_target = None;
+        self.camera = Camera::new(self.player.draw_pos);
+        self.fov = Fov::new(map);
+        self.fog = Fog::new(&self.fov);
+        self.lighting = Lighting::new(&map.walls);
+        self.window_lights = window_lights(map);
+        self.soundscape = Soundscape::new(map);
+    }
+    /// Keeps the music going and settings applied while the game is paused behind a menu
+    fn update_paused(&mut self, settings: &Settings) {
+        self.display.scale_mode = settings.scale_mode;
+        self.display.ui_resolution = settings.ui_resolution;
+        self.audio.volume = settings.sfx_volume;
+        self.audio.music_volume = settings.music_volume;
+        self.audio.begin_frame();
+        self.audio.finish_frame(&self.soundscape);
+    }
+    fn update(&mut self, settings: &Settings, input: &mut Input) {
+        self.display.scale_mode = settings.scale_mode;
+        self.display.ui_resolution = settings.ui_resolution;
+        self.audio.volume = settings.sfx_volume;
+        self.audio.music_volume = settings.music_volume;
+        self.display.begin();
+        self.audio.begin_frame();
+        let delta_time = get_frame_time() * settings.game_speed;
+        self.time += delta_time;
+        if let Some(cutscene) = &mut self.cutscene {
+            // the first press only finishes revealing the caption
+            let advance = self.typewriter.is_finished() && input.take_advance();
+            let skip = input.take(InputAction::Skip);
+            if cutscene.update(delta_time, advance, skip) {
+                match cutscene.cutscene.end {
+                    CutsceneEnd::GiveTag(tag) => self.player.tags.push(tag),
+                    CutsceneEnd::Play(name) => {
+                        *cutscene = CutscenePlayer::covered(cutscene::cutscene(name))
+                    }
+                    CutsceneEnd::Restart => self.restart = true,
+                }
+            }
+            if cutscene.is_finished() {
+                self.cutscene = None;
             }
         }
-        self.time += delta_time;
+        if let Some(change) = &mut self.map_change {
+            let switched = change.update(delta_time);
+            let (name, finished) = (change.target, change.is_finished());
+            if switched {
+                self.enter_map(name);
+            }
+            if finished {
+                self.map_change = None;
+            }
+        }
+        let mut renderer = MacroquadRenderer {
+            assets: self.assets,
+            map: self.map,
+        };
         let mut ctx = DrawCtx {
-            screen_size: vec2(screen_width, screen_height),
-            camera_pos: self.player.draw_pos.floor(),
-            scale_factor,
-            assets: &self.assets,
+            screen_size: vec2(SCREEN_WIDTH, SCREEN_HEIGHT),
+            camera_pos: self.camera.view_pos(),
+            scale_factor: 1.0,
+            sprites: &self.assets.sprites,
+            renderer: &mut renderer,
+            typewriter: &mut self.typewriter,
+            input,
         };
         let interacting_with_any = any_interacting(&self.characters);
-        if let Some((screen, time)) = &mut self.screen
-            && *time > FADE_TIME / 2.0
+        self.interaction_target = None;
+        if let Some(cutscene) = &self.cutscene
+            && cutscene.panel.is_some()
         {
-            let screen = &self.assets.screens[*screen];
-            let size = screen.get_at_time(0).size() * scale_factor * 4.0;
-            draw_texture_ex(
-                &screen.get_at_time((self.time * 1000.0) as u32),
-                (screen_width - size.x) / 2.0,
-                (screen_height - size.y) / 2.0,
-                WHITE,
-                DrawTextureParams {
-                    dest_size: Some(size),
-                    ..Default::default()
-                },
-            );
+            cutscene.draw_screen(&mut ctx);
         } else {
-            if interacting_with_any.is_none() {
-                self.player
-                    .update(delta_time, self.assets, &mut self.characters);
+            self.player.animation_events.clear();
+            // the player stays put while the camera is busy showing something else
+            if interacting_with_any.is_none()
+                && self.camera.following_player()
+                && self.cutscene.is_none()
+                && self.map_change.is_none()
+            {
+                if ctx.input.move_axis() != Vec2::ZERO {
+                    self.click_target = None;
+                }
+                // clicking or tapping a tile walks there, through the letterboxing, zoom and camera
+                if let Some(point) = ctx.input.take_click()
+                    && let Some(frame) = window_to_frame(
+                        self.display.scale_mode,
+                        miniquad::window::screen_size().into(),
+                        point,
+                    )
+                    && let world = self.camera.frame_to_world(frame)
+                    && world.x >= 0.0
+                    && world.y >= 0.0
+                    && let Some(plan) = plan_click(
+                        self.map,
+                        &self.characters,
+                        (self.player.x, self.player.y),
+                        ((world.x / 16.0) as usize, (world.y / 16.0) as usize),
+                    )
+                {
+                    self.player.path = plan.path;
+                    self.click_target = plan.target;
+                }
+                self.player.update(
+                    delta_time,
+                    self.map,
+                    &mut self.characters,
+                    &mut self.audio,
+                    ctx.input,
+                );
             }
-            ctx.camera_pos = self.player.draw_pos.floor();
-            let map = self
-                .assets
-                .map
-                .background_camera
-                .render_target
-                .as_ref()
-                .unwrap();
-
-            // draw vision cones.
-            // i did this by hand and it uses a lot of magic numbers, mb
-            draw_texture_ex(
-                &self.assets.vision_cones,
-                -self.player.draw_pos.x.floor() * scale_factor
-                    + SCREEN_WIDTH * scale_factor / 2.0
-                    + SCREEN_WIDTH * scale_factor / 2.0
-                    - 118.0 * scale_factor
-                    - 12.0 * scale_factor,
-                -self.player.draw_pos.y.floor() * scale_factor
-                    + SCREEN_HEIGHT * scale_factor / 2.0
-                    + SCREEN_HEIGHT * scale_factor / 2.0
-                    - 12.0 * scale_factor,
-                WHITE,
-                DrawTextureParams {
-                    dest_size: Some(self.assets.vision_cones.size() * scale_factor * 1.15),
-                    ..Default::default()
-                },
-            );
-            draw_texture_ex(
-                &self
-                    .assets
-                    .snow_blowing
-                    .get_at_time((self.time * 1000.0) as u32),
-                -self.player.draw_pos.x.floor() * scale_factor
-                    + SCREEN_WIDTH * scale_factor / 2.0
-                    + SCREEN_WIDTH * scale_factor / 2.0
-                    - 118.0 * scale_factor
-                    - 12.0 * scale_factor,
-                -self.player.draw_pos.y.floor() * scale_factor
-                    + SCREEN_HEIGHT * scale_factor / 2.0
-                    + SCREEN_HEIGHT * scale_factor / 2.0
-                    - 12.0 * scale_factor,
-                WHITE.with_alpha(0.5),
-                DrawTextureParams {
-                    dest_size: Some(self.assets.vision_cones.size() * scale_factor * 1.15),
-                    ..Default::default()
-                },
-            );
-            draw_texture_ex(
-                &map.texture,
-                (-self.player.draw_pos.x.floor() * scale_factor
-                    + SCREEN_WIDTH * scale_factor / 2.0)
-                    .floor(),
-                (-self.player.draw_pos.y.floor() * scale_factor
-                    + SCREEN_HEIGHT * scale_factor / 2.0)
-                    .floor(),
-                WHITE,
-                DrawTextureParams {
-                    dest_size: Some(map.texture.size() * scale_factor),
-                    ..Default::default()
-                },
-            );
-            self.player.draw(self.assets, scale_factor);
-            for character in self.characters.iter().filter(|f| !f.draw_over).rev() {
-                character.draw(self.assets, &ctx);
+            // once the player gets to what they clicked on, they turn to it and interact
+            let arrived = self.click_target.filter(|_| self.player.is_still());
+            if let Some(index) = arrived {
+                self.click_target = None;
+                let toward = self.characters[index].draw_pos - self.player.draw_pos;
+                if toward != Vec2::ZERO {
+                    // whichever way it's more of, so it's never diagonal
+                    let toward = if toward.x.abs() > toward.y.abs() {
+                        vec2(toward.x, 0.0)
+                    } else {
+                        vec2(0.0, toward.y)
+                    };
+                    self.player.direction = Direction::from_vec2(toward, Vec2::ZERO);
+                }
             }
-            let map = self
-                .assets
-                .map
-                .foreground_camera
-                .render_target
-                .as_ref()
-                .unwrap();
-            draw_texture_ex(
-                &map.texture,
-                (-self.player.draw_pos.x.floor() * scale_factor
-                    + SCREEN_WIDTH * scale_factor / 2.0)
-                    .floor(),
-                (-self.player.draw_pos.y.floor() * scale_factor
-                    + SCREEN_HEIGHT * scale_factor / 2.0)
-                    .floor(),
-                WHITE,
-                DrawTextureParams {
-                    dest_size: Some(map.texture.size() * scale_factor),
-                    ..Default::default()
-                },
+            self.interaction_target = interaction_target(
+                &self.characters,
+                self.player.draw_pos,
+                self.player.direction,
             );
-            for character in self.characters.iter().filter(|f| f.draw_over).rev() {
-                character.draw(self.assets, &ctx);
+            if arrived.is_some() && arrived == self.interaction_target {
+                ctx.input.press(InputAction::Interact);
+            }
+            let goal = match self.camera.target {
+                CameraTarget::Player => self.player.draw_pos,
+                CameraTarget::Character(index) => self.characters[index].draw_pos,
+                CameraTarget::Point(pos) => pos,
+            };
+            self.camera.update(goal, self.map.bounds(), delta_time);
+            ctx.camera_pos = self.camera.view_pos();
+            self.display.set_zoom(self.camera.zoom);
+            let map = self.map.background_camera.render_target.as_ref().unwrap();
+
+            let map_pos = -ctx.camera_pos + vec2(SCREEN_WIDTH, SCREEN_HEIGHT) / 2.0;
+            let view = self.camera.view();
+            let steam_sources: Vec<Vec2> = self.player.cups_pos().into_iter().collect();
+            self.weather.update(delta_time, view, &steam_sources);
+            for character in self.characters.iter_mut().filter(|f| f.catches_snow) {
+                settle_snow(character, self.map, self.weather.intensity, delta_time);
+            }
+            // snow blowing past outside, only seen where the map is see-through
+            self.weather.draw(ParticleKind::Snow, map_pos);
+            ctx.renderer.draw(DrawCommand::Texture {
+                texture: TextureId::MapBackground,
+                source: None,
+                dest: Rect::new(
+                    map_pos.x,
+                    map_pos.y,
+                    map.texture.width(),
+                    map.texture.height(),
+                ),
+                color: WHITE,
+            });
+            for drawable in depth_sorted(self.player.draw_pos, &self.characters, self.map) {
+                match drawable {
+                    Drawable::Player => self.player.draw(&mut ctx),
+                    Drawable::Character(character) => {
+                        character.draw(&mut ctx);
+                        draw_settled_snow(character, &mut ctx);
+                        if self
+                            .interaction_target
+                            .is_some_and(|f| std::ptr::eq(&self.characters[f], character))
+                        {
+                            character.draw_outline(&mut ctx);
+                        }
+                    }
+                    Drawable::Tile(x, y, tile) => ctx.renderer.draw(DrawCommand::Texture {
+                        texture: TextureId::Tileset,
+                        source: Some(self.map.tileset.tile_source(tile)),
+                        dest: Rect::new(
+                            map_pos.x + (x * 16) as f32,
+                            map_pos.y + (y * 16) as f32,
+                            16.0,
+                            16.0,
+                        ),
+                        color: WHITE,
+                    }),
+                }
             }
+            self.weather.draw(ParticleKind::Steam, map_pos);
+            let lights: Vec<Light> = self
+                .window_lights
+                .iter()
+                .copied()
+                .chain(self.characters.iter().filter_map(|f| {
+                    f.light.map(|light| Light {
+                        pos: f.draw_pos + light.pos,
+                        ..light
+                    })
+                }))
+                .collect();
+            let light_map = self
+                .light_cache
+                .light_map(&self.map.walls, &lights, self.time);
+            self.lighting
+                .draw(&light_map, map_pos.x, map_pos.y, map.texture.size());
+            self.fov
+                .update(&self.map.walls, (self.player.x, self.player.y), delta_time);
+            self.fog
+                .draw(&self.fov, map_pos.x, map_pos.y, map.texture.size());
+            self.display.set_zoom(1.0);
         }
 
-        if let Some((_, time)) = &mut self.screen {
-            *time += delta_time;
-            let amt = (*time / FADE_TIME).min(1.0);
-            let amt = -4.0 * amt.powi(2) + 4.0 * amt;
-            draw_rectangle(0.0, 0.0, screen_width, screen_height, BLACK.with_alpha(amt));
+        if let Some(cutscene) = &self.cutscene {
+            cutscene.draw_transition();
+        }
+        if let Some(change) = &self.map_change {
+            change.draw();
+        }
+
+        // everything from here on is dialogue and tooltips
+        let ui_on_screen = self.display.ui_resolution == UiResolution::Screen;
+        if ui_on_screen {
+            self.display.present();
+            (ctx.screen_size, ctx.scale_factor) = self.display.begin_screen_ui();
+        }
+        if let Some(cutscene) = &self.cutscene {
+            cutscene.draw_caption(&mut ctx);
         }
 
-        for character in self.characters.iter_mut() {
+        let mut advance = take_dialogue_advance(&self.characters, ctx.input);
+        for index in 0..self.characters.len() {
+            let character = &mut self.characters[index];
             character.timer += delta_time;
             let mut reached_destination = false;
             if let Some((x, y)) = &character.moving_to {
@@ -183,13 +366,13 @@ impl<'a> Game<'a> {
 
                 if character.draw_pos.distance(target) <= delta_time * (16.0 / MOVE_TIME) {
                     character.draw_pos = target;
-                    let path = pathfind(
-                        self.assets,
-                        (character.x, character.y),
-                        (*x, *y),
-                        (self.player.x, self.player.y),
-                        character.has_collision,
-                    );
+                    let player = [(self.player.x, self.player.y)];
+                    let blocked: &[_] = if character.has_collision {
+                        &player
+                    } else {
+                        &[]
+                    };
+                    let path = pathfind(self.map, (character.x, character.y), (*x, *y), blocked);
                     if let Some(path) = path.and_then(|f| f.0.get(1).cloned()) {
                         (character.x, character.y) = path;
                     }
@@ -209,17 +392,30 @@ impl<'a> Game<'a> {
 
                 reached_destination = vec2((x * 16) as f32, (y * 16) as f32) == character.draw_pos;
             }
+            character.animation_events.clear();
             if character.animation_playing {
+                let from = (character.anim_time * 1000.0) as u32;
                 character.anim_time += delta_time;
+                if let Some(animation) = character.animation {
+                    character.animation_events = animation.animations[character.animation_index]
+                        .events_between(from, (character.anim_time * 1000.0) as u32);
+                    self.audio.play_events(&character.animation_events);
+                }
+            }
+            // guests can be talked to with interact as well as by walking into them
+            if self.interaction_target == Some(index)
+                && character.interact_prompt().is_none()
+                && interacting_with_any.is_none()
+                && ctx.input.take(InputAction::Interact)
+            {
+                character.talk(self.player.draw_pos);
             }
             if character.interacting
                 && let Some(text) = character.interact_message
+                && draw_dialogue(text, character.name, std::mem::take(&mut advance), &mut ctx)
             {
-                if draw_dialogue(text, character.name, &ctx) {
-                    character.interacting = false;
-                }
+                character.interacting = false;
             }
-            let mut set_time = None;
             let (action_condition, action_event) = character.get_action();
             let mut action_event = *action_event;
             let mut check_condition = |action_condition: &ActionCondition| match action_condition {
@@ -228,13 +424,8 @@ impl<'a> Game<'a> {
                 }
                 ActionCondition::ReachedDestination => reached_destination,
                 ActionCondition::PlayerHasTag(tag) => self.player.tags.contains(tag),
-                ActionCondition::PlayerInteract(text, pos) => {
-                    let dist = self.player.draw_pos.distance_squared(*pos);
-                    if dist <= 350.0 {
-                        draw_tooltip(&text, &ctx)
-                    } else {
-                        false
-                    }
+                ActionCondition::PlayerInteract(text, _) => {
+                    self.interaction_target == Some(index) && draw_tooltip(text, &mut ctx)
                 }
                 ActionCondition::PlayerNear(dist) => {
                     self.player.draw_pos.distance(character.draw_pos) <= *dist
@@ -243,24 +434,25 @@ impl<'a> Game<'a> {
                 ActionCondition::NeverChange => false,
                 ActionCondition::AnimationFinish => {
                     if let Some(animation) = character.animation {
-                        let anim_length =
-                            animation.animations[character.animation_index].total_length as f32;
-                        let result = character.animation_playing
-                            && character.anim_time * 1000.0 >= anim_length;
-                        if result {
-                            set_time = Some((anim_length - 1.0) / 1000.0);
-                        }
-                        result
+                        let animation = &animation.animations[character.animation_index];
+                        character.animation_playing
+                            && animation.is_finished((character.anim_time * 1000.0) as u32)
                     } else {
                         false
                     }
                 }
-                ActionCondition::Dialogue(text) => draw_dialogue(text, character.name, &ctx),
+                ActionCondition::Dialogue(text) => {
+                    draw_dialogue(text, character.name, std::mem::take(&mut advance), &mut ctx)
+                }
+                ActionCondition::AnimationEvent(event) => {
+                    character.animation_events.contains(event)
+                        || self.player.animation_events.contains(event)
+                }
                 ActionCondition::Time(time) => character.timer >= *time,
             };
             let mut complex = || match action_condition {
                 ActionCondition::Else(cond, else_action) => {
-                    if !check_condition(&cond) {
+                    if !check_condition(cond) {
                         action_event = *else_action;
                     }
                     true
@@ -271,7 +463,6 @@ impl<'a> Game<'a> {
                 let mut should_increment_action_index = true;
                 match &action_event {
                     Action::Noop => {}
-                    Action::SetOverlayed(value) => character.draw_over = *value,
                     Action::SetActionIndex(index) => {
                         character.action_index = *index;
                         should_increment_action_index = false;
@@ -284,20 +475,44 @@ impl<'a> Game<'a> {
                     Action::PlayPlayerAnimation(name, tag) => {
                         self.player.time = 0.0;
                         self.player.playing_animation =
-                            Some((self.assets.player.get_by_name(name), *tag));
+                            Some((self.assets.sprite("player").get_by_name(name), *tag));
                     }
                     Action::SetName(name) => character.name = name,
-                    Action::SetAnimationPlaying(value) => character.animation_playing = *value,
-                    Action::FadeToScreen(index) => {
-                        self.time = 0.0;
-                        self.screen = Some((*index, 0.0))
+                    Action::PlaySound(sound) => self.audio.play(sound),
+                    Action::SetAnimationPlaying(value) => {
+                        if let Some(animation) = character.animation {
+                            let animation = &animation.animations[character.animation_index];
+                            let time = (character.anim_time * 1000.0) as u32;
+                            let looping = animation.mode == PlaybackMode::Loop;
+                            // a walk cycle comes to a stand on its first frame
+                            if (*value && !looping && animation.is_finished(time))
+                                || (!*value && looping)
+                            {
+                                character.anim_time = 0.0;
+                            }
+                        }
+                        character.animation_playing = *value;
+                    }
+                    Action::PlayCutscene(name, transition) => {
+                        self.cutscene =
+                            Some(CutscenePlayer::new(cutscene::cutscene(name), *transition))
                     }
-                    Action::ShowScreen(index) => self.screen = Some((*index, FADE_TIME)),
-                    Action::HideScreen => self.screen = None,
                     Action::GiveTag(tag) => self.player.tags.push(*tag),
-                    Action::SetAnimationTime(time) => set_time = Some(*time),
                     Action::SetInteractMessage(msg) => character.interact_message = *msg,
                     Action::SetCollision(value) => character.has_collision = *value,
+                    Action::SetLight(light) => character.light = *light,
+                    Action::SetStormIntensity(value) => self.weather.target_intensity = *value,
+                    Action::CameraFocusSelf => self.camera.target = CameraTarget::Character(index),
+                    Action::CameraPanTo((x, y)) => {
+                        self.camera.target = CameraTarget::Point(vec2(*x as f32, *y as f32) * 16.0)
+                    }
+                    Action::CameraZoom(zoom) => self.camera.target_zoom = *zoom,
+                    Action::CameraReturn => self.camera.reset(),
+                    Action::ShakeCamera(amount) => {
+                        if settings.screen_shake {
+                            self.camera.add_shake(*amount)
+                        }
+                    }
                     Action::Teleport(x, y) => {
                         let x = *x;
                         let y = *y;
@@ -319,20 +534,165 @@ impl<'a> Game<'a> {
                 if should_increment_action_index {
                     character.action_index += 1;
                 }
-                if let Some(time) = set_time {
-                    character.anim_time = time;
-                }
             }
         }
+        if !ui_on_screen {
+            self.display.present();
+        }
+        self.typewriter
+            .update(delta_time, settings.text_speed, &mut self.audio);
+        self.soundscape.update(
+            &self.player.tags,
+            (self.player.x, self.player.y),
+            &self.map.walls,
+            self.weather.intensity,
+            delta_time,
+        );
+        self.audio.finish_frame(&self.soundscape);
+    }
+}
+
+/// Light coming in through every window of `map`
+fn window_lights(map: &Map) -> Vec<Light> {
+    map.special
+        .find_tiles(8)
+        .into_iter()
+        .map(|(x, y)| Light {
+            pos: vec2(x as f32, y as f32) * 16.0 + WINDOW_LIGHT.pos,
+            ..WINDOW_LIGHT
+        })
+        .collect()
+}
+
+fn window_conf() -> Conf {
+    let settings = Settings::load(&*settings_store());
+    Conf {
+        window_title: "winternight".to_string(),
+        window_width: settings.window_size.0 as i32,
+        window_height: settings.window_size.1 as i32,
+        fullscreen: settings.fullscreen,
+        ..Default::default()
     }
 }
 
-#[macroquad::main("winternight")]
+/// Saves settings, along with the size the window is at now
+fn save_settings(settings: &mut Settings, store: &mut dyn settings::SettingsStore) {
+    if !settings.fullscreen {
+        let (w, h) = miniquad::window::screen_size();
+        settings.window_size = (w as u32, h as u32);
+    }
+    settings.save(store);
+}
+
+#[macroquad::main(window_conf)]
 async fn main() {
     let assets = Assets::load();
-    let mut game = Game::new(&assets);
+    #[cfg(feature = "audio")]
+    let audio = audio::MacroquadAudio::load().await;
+    #[cfg(not(feature = "audio"))]
+    let audio = audio::NullAudio;
+    let mut store = settings_store();
+    let mut settings = Settings::load(&*store);
+    // what was last saved, to notice when anything changes
+    let mut saved = settings.clone();
+    let mut fullscreen = settings.fullscreen;
+    let mut menu = Some(Menu::new(Page::Title));
+    let mut game: Option<Game> = None;
+    let mut backdrop = TitleBackdrop::new();
     loop {
-        game.update();
+        // quicker than the settings menu while working on how things look
+        if cfg!(debug_assertions) {
+            if is_key_pressed(KeyCode::F2) {
+                settings.scale_mode = settings.scale_mode.next();
+            }
+            if is_key_pressed(KeyCode::F3) {
+                settings.ui_resolution = settings.ui_resolution.next();
+            }
+        }
+        let mut input = Input::new(&settings.bindings);
+        // a key bound to both skips cutscenes rather than pausing them
+        let skipping = input.is_pressed(InputAction::Skip)
+            && game.as_ref().is_some_and(|f| f.cutscene.is_some());
+        match &mut menu {
+            // the key that paused shouldn't count as a press in the menu too
+            None if !skipping && input.take(InputAction::Pause) => {
+                menu = Some(Menu::new(Page::Pause))
+            }
+            None => {}
+            Some(current) => match current.update(
+                MenuInput::from_keys(&mut input),
+                &mut settings,
+                game.is_some(),
+            ) {
+                Some(MenuEvent::NewGame) => {
+                    game = Some(Game::new(&assets, &audio));
+                    menu = None;
+                }
+                Some(MenuEvent::Resume) => menu = None,
+                Some(MenuEvent::QuitToTitle) => {
+                    if let Some(game) = &game {
+                        game.audio.silence_loops();
+                    }
+                    menu = Some(Menu::new(Page::Title));
+                }
+                Some(MenuEvent::Quit) => {
+                    save_settings(&mut settings, &mut *store);
+                    miniquad::window::order_quit();
+                }
+                None => {}
+            },
+        }
+        if settings.fullscreen != fullscreen {
+            miniquad::window::set_fullscreen(settings.fullscreen);
+            fullscreen = settings.fullscreen;
+        }
+        // saved once the player is done with the settings, not on every step of a slider
+        let changing = menu.as_ref().is_some_and(Menu::in_settings);
+        if settings != saved && !changing {
+            save_settings(&mut settings, &mut *store);
+            saved = settings.clone();
+        }
+        match (&menu, &mut game) {
+            (None, Some(current)) => {
+                current.update(&settings, &mut input);
+                // back to the title once the credits are over
+                if current.restart {
+                    current.audio.silence_loops();
+                    game = None;
+                    menu = Some(Menu::new(Page::Title));
+                }
+            }
+            (Some(current), game) => {
+                let (screen_size, scale_factor) = match game {
+                    Some(game) if current.over_game() => {
+                        game.update_paused(&settings);
+                        game.display.present();
+                        game.display.begin_screen_ui()
+                    }
+                    _ => {
+                        backdrop.display.scale_mode = settings.scale_mode;
+                        backdrop.draw(get_frame_time());
+                        backdrop.display.begin_screen_ui()
+                    }
+                };
+                let mut renderer = MacroquadRenderer {
+                    assets: &assets,
+                    map: assets.map(START_MAP),
+                };
+                let mut typewriter = Typewriter::default();
+                let mut ctx = DrawCtx {
+                    screen_size,
+                    camera_pos: Vec2::ZERO,
+                    scale_factor,
+                    sprites: &assets.sprites,
+                    renderer: &mut renderer,
+                    typewriter: &mut typewriter,
+                    input: &mut input,
+                };
+                current.draw(&mut ctx, &settings, game.is_some());
+            }
+            (None, None) => menu = Some(Menu::new(Page::Title)),
+        }
         next_frame().await
     }
 }
@@ -0,0 +1,412 @@
+// every asset the game loads, listed by name.
+// new content goes here and is then looked up by name through `Assets`.
+
+use crate::{
+    player::Tag,
+    soundscape::{Layer, LayerSource},
+    synth::{Ambience, LoopSound, Melody, SoundEffect, Waveform},
+    voice::Voice,
+};
+
+pub const TILESET: &[u8] = include_bytes!("../assets/tileset.ase");
+
+pub const MAPS: &[(&str, &str)] = &[("house", include_str!("../assets/map.tmx"))];
+
+/// Sprites with tagged animations, loaded as `AnimationsGroup`s
+pub const SPRITES: &[(&str, &[u8])] = &[
+    ("player", include_bytes!("../assets/player.ase")),
+    ("cups", include_bytes!("../assets/cups.ase")),
+    (
+        "raincoat_ferret",
+        include_bytes!("../assets/raincoat_ferret.ase"),
+    ),
+    (
+        "mother_ferret",
+        include_bytes!("../assets/mother_ferret.ase"),
+    ),
+    (
+        "child_ferret1",
+        include_bytes!("../assets/child_ferret1.ase"),
+    ),
+    (
+        "child_ferret2",
+        include_bytes!("../assets/child_ferret2.ase"),
+    ),
+    ("door", include_bytes!("../assets/door.ase")),
+    ("fireplace", include_bytes!("../assets/fireplace.ase")),
+];
+
+pub const SCREENS: &[(&str, &[u8])] = &[
+    (
+        "raincoat_ferret",
+        include_bytes!("../assets/screens/raincoat_ferret.ase"),
+    ),
+    (
+        "raincoat_ferret_happy",
+        include_bytes!("../assets/screens/raincoat_ferret_happy.ase"),
+    ),
+    (
+        "ferret_family",
+        include_bytes!("../assets/screens/ferret_family.ase"),
+    ),
+    (
+        "ferret_family_happy",
+        include_bytes!("../assets/screens/ferret_family_happy.ase"),
+    ),
+    (
+        "child_ferrets_hot_chocolate",
+        include_bytes!("../assets/screens/child_ferrets_hot_chocolate.ase"),
+    ),
+];
+
+pub const FONTS: &[(&str, &[u8])] = &[("tinos", include_bytes!("../assets/tinos.ttf"))];
+
+const QUIET_STEP: SoundEffect = SoundEffect {
+    waveform: Waveform::Noise,
+    pitch: (0.0, 0.0),
+    attack: 0.005,
+    decay: 0.05,
+    vibrato: 0.0,
+    muffle: 0.9,
+    volume: 0.25,
+    repeats: 1,
+    spacing: 0.0,
+};
+
+/// Sound effects, synthesized at load since there are no recordings.
+/// Played by name from scripts, and from frames with a `sound:<name>` event.
+pub const SOUNDS: &[(&str, SoundEffect)] = &[
+    (
+        // three knocks, timed like the door's shakes
+        "knock",
+        SoundEffect {
+            waveform: Waveform::Sine,
+            pitch: (180.0, 70.0),
+            attack: 0.003,
+            decay: 0.09,
+            vibrato: 0.0,
+            muffle: 0.6,
+            volume: 0.9,
+            repeats: 3,
+            spacing: 0.3,
+        },
+    ),
+    (
+        "creak",
+        SoundEffect {
+            waveform: Waveform::Saw,
+            pitch: (220.0, 160.0),
+            attack: 0.05,
+            decay: 0.45,
+            vibrato: 0.08,
+            muffle: 0.7,
+            volume: 0.35,
+            ..QUIET_STEP
+        },
+    ),
+    (
+        "ignite",
+        SoundEffect {
+            attack: 0.08,
+            decay: 0.6,
+            muffle: 0.5,
+            volume: 0.6,
+            ..QUIET_STEP
+        },
+    ),
+    (
+        "pour",
+        SoundEffect {
+            attack: 0.15,
+            decay: 0.9,
+            muffle: 0.85,
+            volume: 0.4,
+            ..QUIET_STEP
+        },
+    ),
+    (
+        "step_wood",
+        SoundEffect {
+            waveform: Waveform::Triangle,
+            pitch: (120.0, 80.0),
+            attack: 0.002,
+            decay: 0.06,
+            muffle: 0.5,
+            volume: 0.35,
+            ..QUIET_STEP
+        },
+    ),
+    ("step_rug", QUIET_STEP),
+    (
+        "step_tile",
+        SoundEffect {
+            waveform: Waveform::Square,
+            pitch: (900.0, 600.0),
+            attack: 0.001,
+            decay: 0.025,
+            muffle: 0.4,
+            volume: 0.2,
+            ..QUIET_STEP
+        },
+    ),
+];
+/// Tiles of the floor and floor decoration layers, and the footstep sound of walking on them.
+/// Decorations win over the floor under them, and anything else sounds like wood.
+pub const FOOTSTEPS: &[(&[u8], &str)] = &[
+    (&[25, 26, 27, 41, 42, 43, 57, 58, 59], "step_rug"),
+    (&[93, 94, 110], "step_tile"),
+];
+pub const DEFAULT_FOOTSTEP: &str = "step_wood";
+
+// the music layers share a tempo and length, so they stay in step while looping together
+const MUSIC_TEMPO: f32 = 76.0;
+const MUSIC_BASS: &[(Option<i32>, f32)] = &[
+    (Some(-21), 4.0),
+    (Some(-24), 4.0),
+    (Some(-28), 4.0),
+    (Some(-26), 4.0),
+];
+const MUSIC_MELODY: &[(Option<i32>, f32)] = &[
+    (Some(7), 1.0),
+    (Some(10), 1.0),
+    (Some(7), 1.0),
+    (Some(3), 1.0),
+    (Some(3), 1.0),
+    (Some(7), 1.0),
+    (Some(0), 2.0),
+    (Some(8), 1.0),
+    (Some(12), 1.0),
+    (Some(8), 1.0),
+    (Some(3), 1.0),
+    (Some(5), 1.0),
+    (Some(2), 1.0),
+    (Some(-2), 2.0),
+];
+const MUSIC_BELLS: &[(Option<i32>, f32)] = &[
+    (Some(15), 0.5),
+    (Some(19), 0.5),
+    (Some(22), 0.5),
+    (Some(19), 0.5),
+    (Some(15), 0.5),
+    (Some(19), 0.5),
+    (Some(22), 0.5),
+    (None, 0.5),
+    (Some(12), 0.5),
+    (Some(15), 0.5),
+    (Some(19), 0.5),
+    (Some(15), 0.5),
+    (Some(12), 0.5),
+    (Some(15), 0.5),
+    (Some(19), 0.5),
+    (None, 0.5),
+    (Some(8), 0.5),
+    (Some(12), 0.5),
+    (Some(15), 0.5),
+    (Some(12), 0.5),
+    (Some(8), 0.5),
+    (Some(12), 0.5),
+    (Some(15), 0.5),
+    (None, 0.5),
+    (Some(10), 0.5),
+    (Some(14), 0.5),
+    (Some(17), 0.5),
+    (Some(14), 0.5),
+    (Some(10), 0.5),
+    (Some(14), 0.5),
+    (Some(17), 0.5),
+    (None, 0.5),
+];
+
+/// Music and ambience, synthesized at load and looped forever. `LAYERS` decides how loud each is.
+pub const LOOPS: &[(&str, LoopSound)] = &[
+    (
+        "music_bass",
+        LoopSound::Melody(Melody {
+            waveform: Waveform::Triangle,
+            notes: MUSIC_BASS,
+            beats_per_minute: MUSIC_TEMPO,
+            pluck: false,
+            vibrato: 0.0,
+            muffle: 0.5,
+            volume: 0.35,
+        }),
+    ),
+    (
+        "music_melody",
+        LoopSound::Melody(Melody {
+            waveform: Waveform::Sine,
+            notes: MUSIC_MELODY,
+            beats_per_minute: MUSIC_TEMPO,
+            pluck: true,
+            vibrato: 0.0,
+            muffle: 0.2,
+            volume: 0.3,
+        }),
+    ),
+    (
+        "music_bells",
+        LoopSound::Melody(Melody {
+            waveform: Waveform::Square,
+            notes: MUSIC_BELLS,
+            beats_per_minute: MUSIC_TEMPO,
+            pluck: true,
+            vibrato: 0.0,
+            muffle: 0.85,
+            volume: 0.12,
+        }),
+    ),
+    (
+        "wind",
+        LoopSound::Ambience(Ambience {
+            seconds: 6.0,
+            muffle: 0.97,
+            swells: 2,
+            pops: 0.0,
+            volume: 0.9,
+        }),
+    ),
+    (
+        "fire",
+        LoopSound::Ambience(Ambience {
+            seconds: 4.0,
+            muffle: 0.9,
+            swells: 0,
+            pops: 12.0,
+            volume: 0.5,
+        }),
+    ),
+    (
+        "kettle",
+        LoopSound::Melody(Melody {
+            waveform: Waveform::Sine,
+            notes: &[(Some(24), 8.0)],
+            beats_per_minute: 60.0,
+            pluck: false,
+            vibrato: 0.01,
+            muffle: 0.0,
+            volume: 0.15,
+        }),
+    ),
+];
+
+const LAYER: Layer = Layer {
+    sound: "",
+    source: LayerSource::Everywhere,
+    after: None,
+    until: None,
+    follows_storm: false,
+    volume: 1.0,
+};
+
+/// When and where each looping sound is heard. Sounds with several layers play as loud as the loudest.
+/// Zones are marked on the map's special layer.
+pub const LAYERS: &[Layer] = &[
+    Layer {
+        sound: "music_bass",
+        volume: 0.8,
+        ..LAYER
+    },
+    // more of the tune comes in as the house fills up
+    Layer {
+        sound: "music_melody",
+        after: Some(Tag::ClosedDoor),
+        volume: 0.8,
+        ..LAYER
+    },
+    Layer {
+        sound: "music_bells",
+        after: Some(Tag::ClosedDoor2),
+        ..LAYER
+    },
+    // the storm howls through the whole house, but mostly by the door and windows
+    Layer {
+        sound: "wind",
+        follows_storm: true,
+        volume: 0.15,
+        ..LAYER
+    },
+    Layer {
+        sound: "wind",
+        source: LayerSource::Zone {
+            special_tile: 2,
+            radius: 10.0,
+        },
+        follows_storm: true,
+        ..LAYER
+    },
+    Layer {
+        sound: "wind",
+        source: LayerSource::Zone {
+            special_tile: 8,
+            radius: 4.0,
+        },
+        follows_storm: true,
+        volume: 0.5,
+        ..LAYER
+    },
+    Layer {
+        sound: "fire",
+        source: LayerSource::Zone {
+            special_tile: 3,
+            radius: 9.0,
+        },
+        after: Some(Tag::LightFire),
+        ..LAYER
+    },
+    Layer {
+        sound: "kettle",
+        source: LayerSource::Zone {
+            special_tile: 7,
+            radius: 6.0,
+        },
+        after: Some(Tag::ChildrenWantChocolate),
+        until: Some(Tag::CarryingCups),
+        volume: 0.8,
+        ..LAYER
+    },
+];
+
+const RAINCOAT_VOICE: Voice = Voice {
+    waveform: Waveform::Triangle,
+    pitch: 330.0,
+    range: 5.0,
+    slide: 1.1,
+    muffle: 0.3,
+    blip_length: 0.05,
+    speed: 28.0,
+};
+
+/// Voices of every speaker that has one, by name as shown on their dialogue
+pub const VOICES: &[(&str, Voice)] = &[
+    ("Ferret in a raincoat", RAINCOAT_VOICE),
+    ("Ferret", RAINCOAT_VOICE),
+    (
+        "Ferret Mother",
+        Voice {
+            waveform: Waveform::Sine,
+            pitch: 250.0,
+            range: 3.0,
+            slide: 0.9,
+            muffle: 0.0,
+            blip_length: 0.07,
+            speed: 22.0,
+        },
+    ),
+    (
+        "Child Ferret",
+        Voice {
+            waveform: Waveform::Square,
+            pitch: 620.0,
+            range: 8.0,
+            slide: 1.2,
+            muffle: 0.6,
+            blip_length: 0.035,
+            speed: 36.0,
+        },
+    ),
+];
+
+/// Name of the map the game starts on
+pub const START_MAP: &str = "house";
+/// Font used for all dialogue and tooltips
+pub const UI_FONT: &str = "tinos";
@@ -0,0 +1,513 @@
+use macroquad::prelude::*;
+
+use crate::{
+    characters::{DIALOGUE_BODY, DIALOGUE_BORDER, DrawCtx},
+    cutscene::cutscene,
+    display::Display,
+    input::{Input, InputAction, default_bindings, key_name},
+    settings::{GAME_SPEED_RANGE, Settings},
+    utils::{SCREEN_HEIGHT, SCREEN_WIDTH},
+    weather::{ParticleKind, Weather},
+};
+
+/// How much a volume changes with every press of left or right
+const VOLUME_STEP: f32 = 0.1;
+const GAME_SPEED_STEP: f32 = 0.25;
+const TITLE: &str = "winternight";
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Page {
+    Title,
+    /// Over the frozen game
+    Pause,
+    Settings,
+    Controls,
+    Credits,
+}
+impl Page {
+    fn heading(self) -> &'static str {
+        match self {
+            Page::Title => TITLE,
+            Page::Pause => "Paused",
+            Page::Settings => "Settings",
+            Page::Controls => "Keys",
+            Page::Credits => "Credits",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Item {
+    Continue,
+    NewGame,
+    Resume,
+    Restart,
+    Settings,
+    Credits,
+    QuitToTitle,
+    Quit,
+    SfxVolume,
+    MusicVolume,
+    ScaleMode,
+    UiResolution,
+    TextSpeed,
+    Language,
+    Fullscreen,
+    ScreenShake,
+    GameSpeed,
+    Controls,
+    /// Changes the keys bound to an action
+    Bind(InputAction),
+    ResetKeys,
+    Back,
+}
+
+/// What the game has to do about a choice made in the menu
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MenuEvent {
+    NewGame,
+    /// Close the menu and carry on with the game there is
+    Resume,
+    QuitToTitle,
+    Quit,
+}
+
+/// Buttons pressed this frame, as far as menus care
+#[derive(Clone, Copy, Default)]
+pub struct MenuInput {
+    pub up: bool,
+    pub down: bool,
+    pub left: bool,
+    pub right: bool,
+    pub select: bool,
+    pub back: bool,
+    /// Last key pressed, for binding it to something
+    pub key: Option<KeyCode>,
+}
+impl MenuInput {
+    /// Takes the presses menus use, so closing one can't also do something in the game.
+    /// Arrows, enter and escape always work in menus, so bad bindings can't lock anyone out.
+    pub fn from_keys(input: &mut Input) -> Self {
+        // menus don't use clicks, but they shouldn't fall through to the game either
+        input.take_click();
+        let mut pressed =
+            |action, keys: &[KeyCode]| input.take(action) | keys.iter().any(|f| is_key_pressed(*f));
+        Self {
+            up: pressed(InputAction::MoveUp, &[KeyCode::Up]),
+            down: pressed(InputAction::MoveDown, &[KeyCode::Down]),
+            left: pressed(InputAction::MoveLeft, &[KeyCode::Left]),
+            right: pressed(InputAction::MoveRight, &[KeyCode::Right]),
+            select: pressed(InputAction::Interact, &[KeyCode::Enter]),
+            // skip too, or escape would skip the cutscene underneath as it resumes
+            back: pressed(InputAction::Pause, &[KeyCode::Escape, KeyCode::Backspace])
+                | input.take(InputAction::Skip),
+            key: get_last_key_pressed(),
+        }
+    }
+}
+
+pub struct Menu {
+    pub page: Page,
+    /// Index into the page's items
+    pub selected: usize,
+    /// Pages to go back to, and what was selected on them
+    history: Vec<(Page, usize)>,
+    /// Action waiting for a key to be pressed to bind or unbind it
+    pub binding: Option<InputAction>,
+}
+impl Menu {
+    pub fn new(page: Page) -> Self {
+        Self {
+            page,
+            selected: 0,
+            history: Vec::new(),
+            binding: None,
+        }
+    }
+    /// Whether the menu was opened from the game, which shows frozen behind it
+    pub fn over_game(&self) -> bool {
+        self.history.first().map_or(self.page, |(page, _)| *page) == Page::Pause
+    }
+    /// Whether the player is changing settings, on the settings page or one opened from it
+    pub fn in_settings(&self) -> bool {
+        matches!(self.page, Page::Settings | Page::Controls)
+    }
+    /// Items on the current page, top to bottom
+    pub fn items(&self, has_game: bool) -> Vec<Item> {
+        match self.page {
+            Page::Title => {
+                let mut items = Vec::new();
+                if has_game {
+                    items.push(Item::Continue);
+                }
+                items.extend([Item::NewGame, Item::Settings, Item::Credits]);
+                // closing the tab is how you quit on the web
+                if !cfg!(target_arch = "wasm32") {
+                    items.push(Item::Quit);
+                }
+                items
+            }
+            Page::Pause => vec![
+                Item::Resume,
+                Item::Restart,
+                Item::Settings,
+                Item::QuitToTitle,
+            ],
+            Page::Settings => vec![
+                Item::SfxVolume,
+                Item::MusicVolume,
+                Item::ScaleMode,
+                Item::UiResolution,
+                Item::TextSpeed,
+                Item::Language,
+                Item::Fullscreen,
+                Item::ScreenShake,
+                Item::GameSpeed,
+                Item::Controls,
+                Item::Back,
+            ],
+            Page::Controls => InputAction::ALL
+                .into_iter()
+                .map(Item::Bind)
+                .chain([Item::ResetKeys, Item::Back])
+                .collect(),
+            Page::Credits => vec![Item::Back],
+        }
+    }
+    /// Text shown above the items
+    pub fn lines(&self) -> Vec<&'static str> {
+        match self.page {
+            Page::Controls => vec![
+                "Pick an action, then press a key to add it.",
+                "Pressing a key it already has takes it away.",
+                "Escape cancels.",
+            ],
+            Page::Credits => cutscene("credits")
+                .panels
+                .iter()
+                .filter_map(|f| f.caption.as_ref().map(|f| f.text))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+    fn open(&mut self, page: Page) {
+        self.history.push((self.page, self.selected));
+        self.page = page;
+        self.selected = 0;
+    }
+    /// Goes back a page. Returns false if there is nowhere to go back to.
+    fn back(&mut self) -> bool {
+        match self.history.pop() {
+            Some((page, selected)) => {
+                (self.page, self.selected) = (page, selected);
+                true
+            }
+            None => false,
+        }
+    }
+    pub fn update(
+        &mut self,
+        input: MenuInput,
+        settings: &mut Settings,
+        has_game: bool,
+    ) -> Option<MenuEvent> {
+        if let Some(action) = self.binding {
+            // back always means back, so it cancels instead of being bound,
+            // and keys that can't be named can't be bound, so they're ignored
+            if input.back {
+                self.binding = None;
+            } else if let Some(key) = input.key.filter(|f| key_name(*f) != "?") {
+                toggle_binding(&mut settings.bindings, action, key);
+                self.binding = None;
+            }
+            return None;
+        }
+        let items = self.items(has_game);
+        self.selected = self.selected.min(items.len() - 1);
+        if input.back && !self.back() && self.page == Page::Pause {
+            return Some(MenuEvent::Resume);
+        }
+        if input.up {
+            self.selected = (self.selected + items.len() - 1) % items.len();
+        }
+        if input.down {
+            self.selected = (self.selected + 1) % items.len();
+        }
+        let item = items[self.selected];
+        if input.left || input.right {
+            let step = if input.right { 1.0 } else { -1.0 };
+            match item {
+                Item::SfxVolume => settings.sfx_volume = adjust_volume(settings.sfx_volume, step),
+                Item::MusicVolume => {
+                    settings.music_volume = adjust_volume(settings.music_volume, step)
+                }
+                Item::ScaleMode => settings.scale_mode = settings.scale_mode.next(),
+                Item::UiResolution => settings.ui_resolution = settings.ui_resolution.next(),
+                Item::TextSpeed if input.right => {
+                    settings.text_speed = settings.text_speed.faster()
+                }
+                Item::TextSpeed => settings.text_speed = settings.text_speed.slower(),
+                Item::Fullscreen => settings.fullscreen = !settings.fullscreen,
+                Item::ScreenShake => settings.screen_shake = !settings.screen_shake,
+                Item::GameSpeed => {
+                    settings.game_speed = (settings.game_speed + step * GAME_SPEED_STEP)
+                        .clamp(GAME_SPEED_RANGE.0, GAME_SPEED_RANGE.1)
+                }
+                _ => {}
+            }
+        }
+        if !input.select {
+            return None;
+        }
+        match item {
+            Item::Continue | Item::Resume => return Some(MenuEvent::Resume),
+            Item::NewGame | Item::Restart => return Some(MenuEvent::NewGame),
+            Item::QuitToTitle => return Some(MenuEvent::QuitToTitle),
+            Item::Quit => return Some(MenuEvent::Quit),
+            Item::Settings => self.open(Page::Settings),
+            Item::Credits => self.open(Page::Credits),
+            Item::Controls => self.open(Page::Controls),
+            Item::Bind(action) => self.binding = Some(action),
+            Item::ResetKeys => settings.bindings = default_bindings(),
+            Item::Back => {
+                self.back();
+            }
+            Item::ScaleMode => settings.scale_mode = settings.scale_mode.next(),
+            Item::UiResolution => settings.ui_resolution = settings.ui_resolution.next(),
+            Item::TextSpeed => settings.text_speed = settings.text_speed.faster(),
+            Item::Fullscreen => settings.fullscreen = !settings.fullscreen,
+            Item::ScreenShake => settings.screen_shake = !settings.screen_shake,
+            Item::SfxVolume | Item::MusicVolume | Item::Language | Item::GameSpeed => {}
+        }
+        None
+    }
+    /// Draws the current page in a box in the middle of the screen
+    pub fn draw(&self, ctx: &mut DrawCtx, settings: &Settings, has_game: bool) {
+        let s = ctx.scale_factor;
+        let line_height = 14.0 * s;
+        let items = self.items(has_game);
+        let lines = self.lines();
+        let w = 200.0 * s;
+        let h = (30.0 + 6.0) * s + (lines.len() + items.len()) as f32 * line_height;
+        let x = ((ctx.screen_size.x - w) / 2.0).floor();
+        let y = ((ctx.screen_size.y - h) / 2.0).floor();
+        ctx.draw_rectangle(x, y, w, h, DIALOGUE_BODY);
+        ctx.draw_rectangle_lines(x, y, w, h, 2.0 * s, DIALOGUE_BORDER);
+
+        let heading_size = if self.page == Page::Title { 24.0 } else { 16.0 };
+        draw_centered(ctx, self.page.heading(), y + 22.0 * s, heading_size);
+        let mut line_y = y + 30.0 * s + line_height * 0.75;
+        for line in lines {
+            draw_centered(ctx, line, line_y, 9.0);
+            line_y += line_height;
+        }
+        for (index, item) in items.iter().enumerate() {
+            let label = match item {
+                Item::Bind(action) if self.binding == Some(*action) => {
+                    format!("{}: press a key", action_label(*action))
+                }
+                _ => item_label(*item, settings),
+            };
+            let label = if index == self.selected {
+                format!("> {label} <")
+            } else {
+                label
+            };
+            draw_centered(ctx, &label, line_y, 11.0);
+            line_y += line_height;
+        }
+    }
+}
+
+fn adjust_volume(volume: f32, step: f32) -> f32 {
+    // rounded so repeated steps land on whole percentages
+    ((volume + step * VOLUME_STEP).clamp(0.0, 1.0) * 10.0).round() / 10.0
+}
+
+fn item_label(item: Item, settings: &Settings) -> String {
+    let percent = |volume: f32| format!("{}%", (volume * 100.0).round());
+    match item {
+        Item::Continue => "Continue".to_string(),
+        Item::NewGame => "New game".to_string(),
+        Item::Resume => "Resume".to_string(),
+        Item::Restart => "Restart".to_string(),
+        Item::Settings => "Settings".to_string(),
+        Item::Credits => "Credits".to_string(),
+        Item::QuitToTitle => "Quit to title".to_string(),
+        Item::Quit => "Quit".to_string(),
+        Item::SfxVolume => format!("Effects volume: {}", percent(settings.sfx_volume)),
+        Item::MusicVolume => format!("Music volume: {}", percent(settings.music_volume)),
+        Item::ScaleMode => format!("Scaling: {}", settings.scale_mode.name()),
+        Item::UiResolution => format!("Text resolution: {}", settings.ui_resolution.name()),
+        Item::TextSpeed => format!("Text speed: {}", settings.text_speed.name()),
+        Item::Language => format!("Language: {}", settings.language.name()),
+        Item::Fullscreen => format!("Fullscreen: {}", on_off(settings.fullscreen)),
+        Item::ScreenShake => format!("Screen shake: {}", on_off(settings.screen_shake)),
+        Item::GameSpeed => format!("Game speed: {}", percent(settings.game_speed)),
+        Item::Controls => "Keys".to_string(),
+        Item::Bind(action) => format!("{}: {}", action_label(action), settings.key_names(action)),
+        Item::ResetKeys => "Reset keys".to_string(),
+        Item::Back => "Back".to_string(),
+    }
+}
+
+fn action_label(action: InputAction) -> &'static str {
+    match action {
+        InputAction::MoveUp => "Move up",
+        InputAction::MoveDown => "Move down",
+        InputAction::MoveLeft => "Move left",
+        InputAction::MoveRight => "Move right",
+        InputAction::Interact => "Interact",
+        InputAction::Pause => "Pause",
+        InputAction::Skip => "Skip cutscene",
+    }
+}
+
+/// Binds `key` to `action`, or unbinds it if it already was. The last key of an action stays bound.
+pub fn toggle_binding(
+    bindings: &mut Vec<(InputAction, KeyCode)>,
+    action: InputAction,
+    key: KeyCode,
+) {
+    let bound = bindings
+        .iter()
+        .filter(|(bound, _)| *bound == action)
+        .count();
+    match bindings.iter().position(|f| *f == (action, key)) {
+        Some(_) if bound == 1 => {}
+        Some(index) => {
+            bindings.remove(index);
+        }
+        None => bindings.push((action, key)),
+    }
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value { "on" } else { "off" }
+}
+
+/// Draws a line of text centred across the screen, `font_size` in frame pixels
+fn draw_centered(ctx: &mut DrawCtx, text: &str, y: f32, font_size: f32) {
+    let font_size = (font_size * ctx.scale_factor) as u16;
+    let size = ctx.measure_text(text, font_size);
+    ctx.draw_text(
+        text,
+        ((ctx.screen_size.x - size.width) / 2.0).floor(),
+        y.floor(),
+        font_size,
+    );
+}
+
+/// Snow falling on black behind the title screen
+pub struct TitleBackdrop {
+    pub display: Display,
+    weather: Weather,
+}
+impl TitleBackdrop {
+    pub fn new() -> Self {
+        Self {
+            display: Display::new(),
+            weather: Weather::new(miniquad::date::now() as u64, 0.5),
+        }
+    }
+    pub fn draw(&mut self, delta_time: f32) {
+        self.display.begin();
+        let view = Rect::new(0.0, 0.0, SCREEN_WIDTH, SCREEN_HEIGHT);
+        self.weather.update(delta_time, view, &[]);
+        self.weather.draw(ParticleKind::Snow, Vec2::ZERO);
+        self.display.present();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BACK: MenuInput = MenuInput {
+        back: true,
+        key: Some(KeyCode::Escape),
+        ..NOTHING
+    };
+    const SELECT: MenuInput = MenuInput {
+        select: true,
+        ..NOTHING
+    };
+    const NOTHING: MenuInput = MenuInput {
+        up: false,
+        down: false,
+        left: false,
+        right: false,
+        select: false,
+        back: false,
+        key: None,
+    };
+
+    fn keys(bindings: &[(InputAction, KeyCode)], action: InputAction) -> Vec<KeyCode> {
+        bindings
+            .iter()
+            .filter(|(bound, _)| *bound == action)
+            .map(|(_, key)| *key)
+            .collect()
+    }
+
+    #[test]
+    fn toggling_adds_and_takes_away() {
+        let mut bindings = default_bindings();
+        toggle_binding(&mut bindings, InputAction::MoveUp, KeyCode::W);
+        assert_eq!(keys(&bindings, InputAction::MoveUp), [KeyCode::Up]);
+        toggle_binding(&mut bindings, InputAction::MoveUp, KeyCode::I);
+        assert_eq!(
+            keys(&bindings, InputAction::MoveUp),
+            [KeyCode::Up, KeyCode::I]
+        );
+    }
+
+    #[test]
+    fn last_key_stays_bound() {
+        let mut bindings = vec![(InputAction::Interact, KeyCode::E)];
+        toggle_binding(&mut bindings, InputAction::Interact, KeyCode::E);
+        assert_eq!(bindings, [(InputAction::Interact, KeyCode::E)]);
+    }
+
+    #[test]
+    fn escape_cancels_binding() {
+        let mut settings = Settings::default();
+        let mut menu = Menu::new(Page::Controls);
+        menu.binding = Some(InputAction::Interact);
+        assert_eq!(menu.update(BACK, &mut settings, true), None);
+        assert_eq!(menu.binding, None);
+        assert_eq!(settings.bindings, default_bindings());
+        // and backs out of nothing else while it's at it
+        assert_eq!(menu.page, Page::Controls);
+
+        menu.binding = Some(InputAction::Interact);
+        let q = MenuInput {
+            key: Some(KeyCode::Q),
+            ..NOTHING
+        };
+        menu.update(q, &mut settings, true);
+        assert_eq!(
+            keys(&settings.bindings, InputAction::Interact),
+            [KeyCode::E, KeyCode::Q]
+        );
+    }
+
+    #[test]
+    fn back_out_of_pause_resumes() {
+        let mut settings = Settings::default();
+        let mut menu = Menu::new(Page::Pause);
+        menu.selected = menu
+            .items(true)
+            .iter()
+            .position(|f| *f == Item::Settings)
+            .unwrap();
+        assert_eq!(menu.update(SELECT, &mut settings, true), None);
+        assert_eq!(menu.page, Page::Settings);
+        assert_eq!(menu.update(BACK, &mut settings, true), None);
+        assert_eq!(menu.page, Page::Pause);
+        assert_eq!(
+            menu.update(BACK, &mut settings, true),
+            Some(MenuEvent::Resume)
+        );
+    }
+}
@@ -1,8 +1,10 @@
 use macroquad::prelude::*;
 
 use crate::{
-    assets::{Animation, Assets},
-    characters::{Character, any_interacting},
+    assets::{Animation, Map},
+    audio::{Audio, footstep_sound},
+    characters::{Character, DrawCtx, any_interacting},
+    input::Input,
     utils::*,
 };
 
@@ -53,7 +55,7 @@ impl Direction {
             if last.x != 0.0 { y_dir } else { x_dir }
         }
     }
-    pub fn to_vec2(&self) -> Vec2 {
+    pub fn to_vec2(self) -> Vec2 {
         match self {
             Direction::Up => vec2(0.0, -1.0),
             Direction::Down => vec2(0.0, 1.0),
@@ -68,6 +70,8 @@ pub enum PlayerState {
 }
 
 pub const MOVE_TIME: f32 = 0.25;
+/// Top of the cups of hot chocolate, relative to `draw_pos`
+const CUPS_OFFSET: Vec2 = Vec2::new(8.0, 8.0);
 
 pub struct Player<'a> {
     pub tags: Vec<Tag>,
@@ -78,6 +82,10 @@ pub struct Player<'a> {
     pub time: f32,
     pub state: PlayerState,
     pub playing_animation: Option<(&'a Animation, Tag)>,
+    /// Events from frames of `playing_animation` that started playing this frame
+    pub animation_events: Vec<&'a str>,
+    /// Tiles still to walk through to get where the player clicked, next one first
+    pub path: Vec<(usize, usize)>,
 }
 impl<'a> Player<'a> {
     pub fn new((x, y): (usize, usize)) -> Self {
@@ -90,39 +98,80 @@ impl<'a> Player<'a> {
             time: 0.0,
             state: PlayerState::Idle,
             playing_animation: None,
+            animation_events: Vec::new(),
+            path: Vec::new(),
         }
     }
-    pub fn update(&mut self, delta_time: f32, assets: &'a Assets, characters: &mut Vec<Character>) {
+    /// Whether the player is standing still with nowhere left to walk to
+    pub fn is_still(&self) -> bool {
+        matches!(self.state, PlayerState::Idle) && self.path.is_empty()
+    }
+    /// Direction of the next step along `path`, dropping the path if it doesn't lead on from here
+    fn path_step(&mut self) -> Vec2 {
+        let Some((x, y)) = self.path.first() else {
+            return Vec2::ZERO;
+        };
+        let step = vec2(*x as f32 - self.x as f32, *y as f32 - self.y as f32);
+        if step.length_squared() != 1.0 {
+            self.path.clear();
+            return Vec2::ZERO;
+        }
+        step
+    }
+    pub fn update(
+        &mut self,
+        delta_time: f32,
+        map: &Map,
+        characters: &mut [Character],
+        audio: &mut Audio<'a>,
+        input: &Input,
+    ) {
+        let from = (self.time * 1000.0) as u32;
         self.time += delta_time;
-        let interacting_with_any = any_interacting(&characters).is_some();
+        if let Some((animation, _)) = self.playing_animation {
+            self.animation_events = animation.events_between(from, (self.time * 1000.0) as u32);
+            audio.play_events(&self.animation_events);
+        }
+        let interacting_with_any = any_interacting(characters).is_some();
         match self.state {
             PlayerState::Idle => {
-                let axis = get_input_axis();
+                let mut axis = input.move_axis();
+                // keys take over from walking somewhere clicked
+                if axis != Vec2::ZERO {
+                    self.path.clear();
+                }
+                let following_path = axis == Vec2::ZERO && !self.path.is_empty();
+                if following_path {
+                    axis = self.path_step();
+                }
                 if self.playing_animation.is_none() && axis != Vec2::ZERO {
                     self.direction = Direction::from_vec2(axis, self.direction.to_vec2());
                     let dir = self.direction.to_vec2();
                     let new_x = self.x.saturating_add_signed(dir.x as isize);
                     let new_y = self.y.saturating_add_signed(dir.y as isize);
 
-                    if assets.map.walls.0[new_x + new_y * assets.map.walls.1] == 0 {
+                    if map.walls.0[new_x + new_y * map.walls.1] == 0 {
                         if let Some(character) = characters
                             .iter_mut()
                             .find(|f| f.has_collision && f.x == new_x && f.y == new_y)
                         {
-                            if !interacting_with_any && character.interact_message.is_some() {
-                                character.interacting = true;
-                                let dir = Direction::from_vec2(
-                                    (self.draw_pos - character.draw_pos).normalize(),
-                                    Vec2::ZERO,
-                                )
-                                .name();
-                                character.animation_index =
-                                    character.animation.unwrap().tag_names[dir];
+                            // a clicked path only gets blocked, talking is for walking into someone on purpose
+                            if following_path {
+                                self.path.clear();
+                            } else if !interacting_with_any && character.interact_message.is_some()
+                            {
+                                character.talk(self.draw_pos);
                             }
                         } else {
                             (self.x, self.y) = (new_x, new_y);
                             self.state = PlayerState::Moving;
+                            if following_path {
+                                self.path.remove(0);
+                            }
+                            audio.play(footstep_sound(map, new_x, new_y));
                         }
+                    } else if following_path {
+                        self.path.clear();
                     }
                 }
             }
@@ -139,20 +188,28 @@ impl<'a> Player<'a> {
             }
         }
     }
-    pub fn draw(&mut self, assets: &Assets, scale_factor: f32) {
+    /// World position the steam of the hot chocolate rises from, while carrying it
+    pub fn cups_pos(&self) -> Option<Vec2> {
+        self.tags
+            .contains(&Tag::CarryingCups)
+            .then(|| self.draw_pos + CUPS_OFFSET)
+    }
+    pub fn draw(&mut self, ctx: &mut DrawCtx) {
+        let sprites = ctx.sprites;
+        // the player stands at the middle of the screen unless the camera has moved off
+        let screen_pos =
+            self.draw_pos.floor() - ctx.camera_pos + vec2(SCREEN_WIDTH, SCREEN_HEIGHT) / 2.0;
         if let Some((animation, on_finish_tag)) = &self.playing_animation {
             let anim = animation.get_at_time((self.time * 1000.0) as u32);
-            draw_texture_ex(
-                anim,
-                SCREEN_WIDTH * scale_factor / 2.0,
-                SCREEN_HEIGHT * scale_factor / 2.0 - 16.0 * scale_factor,
+            let offset = TILE_ANCHOR - sprites.sprite("player").pivot;
+            ctx.draw_region(
+                anim.region,
+                screen_pos.x + offset.x,
+                screen_pos.y + offset.y,
                 WHITE,
-                DrawTextureParams {
-                    dest_size: Some(anim.size() * scale_factor),
-                    ..Default::default()
-                },
+                anim.size,
             );
-            if self.time * 1000.0 >= animation.total_length as f32 {
+            if animation.is_finished((self.time * 1000.0) as u32) {
                 self.tags.push(*on_finish_tag);
                 self.playing_animation = None;
             }
@@ -163,33 +220,28 @@ impl<'a> Player<'a> {
         } else {
             0
         };
-        let anim = assets
-            .player
+        let sprite = sprites.sprite("player");
+        let anim = sprite
             .get_by_name(self.direction.name())
             .get_at_time(anim_frame);
-        draw_texture_ex(
-            anim,
-            SCREEN_WIDTH * scale_factor / 2.0,
-            SCREEN_HEIGHT * scale_factor / 2.0 - 16.0 * scale_factor,
+        let offset = TILE_ANCHOR - sprite.pivot;
+        ctx.draw_region(
+            anim.region,
+            screen_pos.x + offset.x,
+            screen_pos.y + offset.y,
             WHITE,
-            DrawTextureParams {
-                dest_size: Some(anim.size() * scale_factor),
-                ..Default::default()
-            },
+            anim.size,
         );
         if self.tags.contains(&Tag::CarryingCups) {
-            draw_texture_ex(
-                &assets
-                    .cups
-                    .get_by_name(self.direction.name())
-                    .get_at_time(0),
-                SCREEN_WIDTH * scale_factor / 2.0,
-                SCREEN_HEIGHT * scale_factor / 2.0,
+            let cups = sprites.sprite("cups");
+            let anim = cups.get_by_name(self.direction.name()).get_at_time(0);
+            let offset = TILE_ANCHOR - cups.pivot;
+            ctx.draw_region(
+                anim.region,
+                screen_pos.x + offset.x,
+                screen_pos.y + offset.y,
                 WHITE,
-                DrawTextureParams {
-                    dest_size: Some(vec2(16.0, 16.0) * scale_factor),
-                    ..Default::default()
-                },
+                anim.size,
             );
         }
     }
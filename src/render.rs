@@ -0,0 +1,281 @@
+use std::fmt::Write;
+
+use macroquad::prelude::*;
+
+use crate::{
+    assets::{Assets, Map},
+    manifest::UI_FONT,
+};
+
+/// Textures the game draws from, by name rather than by GPU handle,
+/// so recorded draw commands mean the same thing on every machine
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TextureId {
+    AtlasPage(usize),
+    /// Tileset of the current map
+    Tileset,
+    /// Floor and floor decorations of the current map, drawn ahead of time
+    MapBackground,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum DrawCommand {
+    Texture {
+        texture: TextureId,
+        /// Part of the texture to draw, or all of it
+        source: Option<Rect>,
+        dest: Rect,
+        color: Color,
+    },
+    Rect {
+        rect: Rect,
+        color: Color,
+    },
+    RectLines {
+        rect: Rect,
+        thickness: f32,
+        color: Color,
+    },
+    /// Text in the UI font, starting on the baseline at `pos`. Can span several lines.
+    Text {
+        text: String,
+        pos: Vec2,
+        font_size: u16,
+        color: Color,
+    },
+}
+
+/// Something that carries out draw commands
+pub trait Renderer {
+    fn draw(&mut self, command: DrawCommand);
+    /// Size of `text` in the UI font, for laying things out around it
+    fn measure_text(&self, text: &str, font_size: u16) -> TextDimensions;
+}
+
+/// Draws to the screen through macroquad
+pub struct MacroquadRenderer<'a> {
+    pub assets: &'a Assets,
+    pub map: &'a Map,
+}
+impl Renderer for MacroquadRenderer<'_> {
+    fn draw(&mut self, command: DrawCommand) {
+        match command {
+            DrawCommand::Texture {
+                texture,
+                source,
+                dest,
+                color,
+            } => {
+                let texture = match texture {
+                    TextureId::AtlasPage(page) => &self.assets.atlas_pages[page],
+                    TextureId::Tileset => &self.map.tileset.texture,
+                    TextureId::MapBackground => {
+                        &self
+                            .map
+                            .background_camera
+                            .render_target
+                            .as_ref()
+                            .unwrap()
+                            .texture
+                    }
+                };
+                draw_texture_ex(
+                    texture,
+                    dest.x,
+                    dest.y,
+                    color,
+                    DrawTextureParams {
+                        source,
+                        dest_size: Some(dest.size()),
+                        ..Default::default()
+                    },
+                );
+            }
+            DrawCommand::Rect { rect, color } => {
+                draw_rectangle(rect.x, rect.y, rect.w, rect.h, color)
+            }
+            DrawCommand::RectLines {
+                rect,
+                thickness,
+                color,
+            } => draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, thickness, color),
+            DrawCommand::Text {
+                text,
+                pos,
+                font_size,
+                color,
+            } => draw_multiline_text_ex(
+                &text,
+                pos.x,
+                pos.y,
+                None,
+                TextParams {
+                    font: Some(self.assets.font(UI_FONT)),
+                    font_size,
+                    color,
+                    ..Default::default()
+                },
+            ),
+        }
+    }
+    fn measure_text(&self, text: &str, font_size: u16) -> TextDimensions {
+        measure_text(text, Some(self.assets.font(UI_FONT)), font_size, 1.0)
+    }
+}
+
+/// Keeps every draw command instead of drawing it, for comparing frames without a window
+#[cfg_attr(not(test), allow(dead_code))]
+#[derive(Default)]
+pub struct Recorder {
+    pub commands: Vec<DrawCommand>,
+}
+impl Renderer for Recorder {
+    fn draw(&mut self, command: DrawCommand) {
+        self.commands.push(command);
+    }
+    /// There's no font without a window, so every letter counts as half as wide as it is tall.
+    /// Recorded frames don't change with the font that way either.
+    fn measure_text(&self, text: &str, font_size: u16) -> TextDimensions {
+        let longest = text.lines().map(|f| f.chars().count()).max().unwrap_or(0);
+        TextDimensions {
+            width: longest as f32 * font_size as f32 / 2.0,
+            height: font_size as f32,
+            offset_y: font_size as f32 * 0.75,
+        }
+    }
+}
+#[cfg_attr(not(test), allow(dead_code))]
+impl Recorder {
+    /// The recorded commands, one per line, in a form that can be stored and compared later
+    pub fn to_golden(&self) -> String {
+        let mut golden = String::new();
+        for command in &self.commands {
+            writeln!(golden, "{command:?}").unwrap();
+        }
+        golden
+    }
+    /// First line where the recorded commands differ from a stored golden list,
+    /// as (line number, expected, actual)
+    pub fn compare(&self, golden: &str) -> Option<(usize, String, String)> {
+        let actual = self.to_golden();
+        let (mut expected_lines, mut actual_lines) = (golden.lines(), actual.lines());
+        for line in 0.. {
+            match (expected_lines.next(), actual_lines.next()) {
+                (None, None) => return None,
+                (expected, actual) if expected != actual => {
+                    return Some((
+                        line,
+                        expected.unwrap_or_default().to_string(),
+                        actual.unwrap_or_default().to_string(),
+                    ));
+                }
+                _ => {}
+            }
+        }
+        unreachable!()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        assets::Sprites,
+        characters::{BASE_CHARACTER, Character, DrawCtx, draw_dialogue, draw_tooltip},
+        input::{Input, default_bindings},
+        menu::{Menu, Page},
+        player::Player,
+        settings::Settings,
+        utils::{SCREEN_HEIGHT, SCREEN_WIDTH},
+        voice::Typewriter,
+    };
+
+    /// Records whatever `draw` draws, at the frame's own resolution with nothing pressed
+    fn record(sprites: &Sprites, draw: impl FnOnce(&mut DrawCtx)) -> Recorder {
+        let mut recorder = Recorder::default();
+        let mut typewriter = Typewriter::default();
+        let mut input = Input::idle(&default_bindings());
+        let mut ctx = DrawCtx {
+            screen_size: vec2(SCREEN_WIDTH, SCREEN_HEIGHT),
+            camera_pos: Vec2::ZERO,
+            scale_factor: 1.0,
+            sprites,
+            renderer: &mut recorder,
+            typewriter: &mut typewriter,
+            input: &mut input,
+        };
+        draw(&mut ctx);
+        recorder
+    }
+
+    /// Compares against `golden/<name>.golden`. Running with `UPDATE_GOLDEN` set writes
+    /// the recorded frame there instead, for when a change to how things look is meant.
+    fn check_golden(name: &str, recorder: &Recorder) {
+        let path = format!("{}/golden/{name}.golden", env!("CARGO_MANIFEST_DIR"));
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::write(&path, recorder.to_golden()).unwrap();
+            return;
+        }
+        let golden = std::fs::read_to_string(&path)
+            .unwrap_or_else(|err| panic!("couldn't read {path}: {err}"));
+        if let Some((line, expected, actual)) = recorder.compare(&golden) {
+            panic!(
+                "{name} differs from {path} at command {line}:\n  expected {expected}\n  got      {actual}"
+            );
+        }
+    }
+
+    #[test]
+    fn dialogue() {
+        let (sprites, _) = Sprites::load();
+        let text = "Could you please light the fireplace?\nI'm so cold.";
+        let recorder = record(&sprites, |ctx| {
+            ctx.typewriter.reveal(text, "Ferret");
+            ctx.typewriter.finish(text);
+            draw_dialogue(text, "Ferret", false, ctx);
+        });
+        check_golden("dialogue", &recorder);
+    }
+
+    #[test]
+    fn tooltip() {
+        let (sprites, _) = Sprites::load();
+        let recorder = record(&sprites, |ctx| {
+            draw_tooltip("open door", ctx);
+        });
+        check_golden("tooltip", &recorder);
+    }
+
+    #[test]
+    fn characters() {
+        let (sprites, _) = Sprites::load();
+        let door = Character {
+            animation: Some(sprites.sprite("door")),
+            draw_pos: vec2(-32.0, -16.0),
+            ..BASE_CHARACTER
+        };
+        let ferret = Character {
+            animation: Some(sprites.sprite("raincoat_ferret")),
+            animation_index: 3,
+            draw_pos: vec2(16.0, 0.0),
+            ..BASE_CHARACTER
+        };
+        let mut player = Player::new((0, 0));
+        let recorder = record(&sprites, |ctx| {
+            door.draw(ctx);
+            door.draw_outline(ctx);
+            ferret.draw(ctx);
+            player.draw(ctx);
+        });
+        check_golden("characters", &recorder);
+    }
+
+    #[test]
+    fn title_menu() {
+        let (sprites, _) = Sprites::load();
+        let recorder = record(&sprites, |ctx| {
+            Menu::new(Page::Title).draw(ctx, &Settings::default(), true)
+        });
+        check_golden("title_menu", &recorder);
+    }
+}
@@ -0,0 +1,453 @@
+use macroquad::{logging::warn, prelude::KeyCode};
+
+use crate::{
+    display::{ScaleMode, UiResolution},
+    input::{InputAction, default_bindings, key_from_name, key_name},
+};
+
+/// Written into every settings file, and checked when reading one. Bump it if a setting
+/// ever changes meaning, and translate older keys in `Settings::load`.
+const SETTINGS_VERSION: u32 = 1;
+/// Slowest and fastest the game can be set to run
+pub const GAME_SPEED_RANGE: (f32, f32) = (0.5, 2.0);
+
+/// How fast dialogue types itself out
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TextSpeed {
+    Slow,
+    Normal,
+    Fast,
+    /// Shows the whole line straight away
+    Instant,
+}
+impl TextSpeed {
+    pub const ALL: [TextSpeed; 4] = [
+        TextSpeed::Slow,
+        TextSpeed::Normal,
+        TextSpeed::Fast,
+        TextSpeed::Instant,
+    ];
+    pub fn name(self) -> &'static str {
+        match self {
+            TextSpeed::Slow => "slow",
+            TextSpeed::Normal => "normal",
+            TextSpeed::Fast => "fast",
+            TextSpeed::Instant => "instant",
+        }
+    }
+    /// How much faster than the speaker's own speed text is revealed, or None to show it all at once
+    pub fn multiplier(self) -> Option<f32> {
+        match self {
+            TextSpeed::Slow => Some(0.6),
+            TextSpeed::Normal => Some(1.0),
+            TextSpeed::Fast => Some(1.8),
+            TextSpeed::Instant => None,
+        }
+    }
+    pub fn faster(self) -> Self {
+        match self {
+            TextSpeed::Slow => TextSpeed::Normal,
+            TextSpeed::Normal => TextSpeed::Fast,
+            TextSpeed::Fast | TextSpeed::Instant => TextSpeed::Instant,
+        }
+    }
+    pub fn slower(self) -> Self {
+        match self {
+            TextSpeed::Slow | TextSpeed::Normal => TextSpeed::Slow,
+            TextSpeed::Fast => TextSpeed::Normal,
+            TextSpeed::Instant => TextSpeed::Fast,
+        }
+    }
+}
+
+/// Language of dialogue and menus. Only English is written so far.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Language {
+    English,
+}
+impl Language {
+    pub const ALL: [Language; 1] = [Language::English];
+    pub fn name(self) -> &'static str {
+        match self {
+            Language::English => "English",
+        }
+    }
+}
+
+/// Everything the player can change from the settings menu
+#[derive(Clone, PartialEq, Debug)]
+pub struct Settings {
+    /// Volume of sound effects and voices, from 0 to 1
+    pub sfx_volume: f32,
+    /// Volume of music and ambience, from 0 to 1
+    pub music_volume: f32,
+    pub scale_mode: ScaleMode,
+    /// Whether dialogue and tooltips are drawn at the frame's pixels or the window's
+    pub ui_resolution: UiResolution,
+    pub text_speed: TextSpeed,
+    pub language: Language,
+    /// Every key bound to each action. An action can have several.
+    pub bindings: Vec<(InputAction, KeyCode)>,
+    pub fullscreen: bool,
+    /// Size of the window when it isn't fullscreen, as it was last left
+    pub window_size: (u32, u32),
+    /// Whether the camera shakes, off for anyone it makes uncomfortable
+    pub screen_shake: bool,
+    /// How fast everything in the game runs, within `GAME_SPEED_RANGE`
+    pub game_speed: f32,
+}
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            sfx_volume: 0.8,
+            music_volume: 0.6,
+            scale_mode: ScaleMode::Fit,
+            ui_resolution: UiResolution::Screen,
+            text_speed: TextSpeed::Normal,
+            language: Language::English,
+            bindings: default_bindings(),
+            fullscreen: false,
+            window_size: (800, 600),
+            screen_shake: true,
+            game_speed: 1.0,
+        }
+    }
+}
+impl Settings {
+    /// Reads settings from `store`. Anything missing or that doesn't make sense keeps its default,
+    /// so files from other versions load whatever they have in common with this one.
+    pub fn load(store: &dyn SettingsStore) -> Self {
+        let defaults = Self::default();
+        // a file without one was written by hand for this version. nothing has changed meaning
+        // yet, so older files read the same; translate their keys here once something does
+        let version = store
+            .get("version")
+            .and_then(|f| f.parse::<u32>().ok())
+            .unwrap_or(SETTINGS_VERSION);
+        if version > SETTINGS_VERSION {
+            warn!("settings are from a newer version ({version}), so some may go back to defaults");
+        }
+        let number = |key: &str, range: (f32, f32)| {
+            store
+                .get(key)
+                .and_then(|f| f.parse::<f32>().ok())
+                .filter(|f| f.is_finite())
+                .map(|f| f.clamp(range.0, range.1))
+        };
+        let boolean = |key: &str| store.get(key).and_then(|f| f.parse::<bool>().ok());
+
+        let mut bindings = Vec::new();
+        for action in InputAction::ALL {
+            let keys: Vec<KeyCode> = store
+                .get(&format!("bind_{}", action.name()))
+                .map(|f| {
+                    f.split(',')
+                        .filter_map(|f| key_from_name(f.trim()))
+                        .collect()
+                })
+                .unwrap_or_default();
+            // an action nothing is bound to could leave the game unplayable
+            let keys = if keys.is_empty() {
+                action.default_keys().to_vec()
+            } else {
+                keys
+            };
+            bindings.extend(keys.into_iter().map(|key| (action, key)));
+        }
+        let window_size = store.get("window_size").and_then(|f| {
+            let (w, h) = f.split_once('x')?;
+            Some((w.trim().parse().ok()?, h.trim().parse().ok()?))
+        });
+
+        Self {
+            sfx_volume: number("sfx_volume", (0.0, 1.0)).unwrap_or(defaults.sfx_volume),
+            music_volume: number("music_volume", (0.0, 1.0)).unwrap_or(defaults.music_volume),
+            scale_mode: parse_named(store.get("scale_mode"), &ScaleMode::ALL, |f| f.name())
+                .unwrap_or(defaults.scale_mode),
+            ui_resolution: parse_named(store.get("ui_resolution"), &UiResolution::ALL, |f| {
+                f.name()
+            })
+            .unwrap_or(defaults.ui_resolution),
+            text_speed: parse_named(store.get("text_speed"), &TextSpeed::ALL, |f| f.name())
+                .unwrap_or(defaults.text_speed),
+            language: parse_named(store.get("language"), &Language::ALL, |f| f.name())
+                .unwrap_or(defaults.language),
+            bindings,
+            fullscreen: boolean("fullscreen").unwrap_or(defaults.fullscreen),
+            window_size: window_size
+                .filter(|(w, h)| *w > 0 && *h > 0)
+                .unwrap_or(defaults.window_size),
+            screen_shake: boolean("screen_shake").unwrap_or(defaults.screen_shake),
+            game_speed: number("game_speed", GAME_SPEED_RANGE).unwrap_or(defaults.game_speed),
+        }
+    }
+    /// Writes every setting into `store` and saves it
+    pub fn save(&self, store: &mut dyn SettingsStore) {
+        store.set("version", SETTINGS_VERSION.to_string());
+        store.set("sfx_volume", self.sfx_volume.to_string());
+        store.set("music_volume", self.music_volume.to_string());
+        store.set("scale_mode", self.scale_mode.name().to_string());
+        store.set("ui_resolution", self.ui_resolution.name().to_string());
+        store.set("text_speed", self.text_speed.name().to_string());
+        store.set("language", self.language.name().to_string());
+        for action in InputAction::ALL {
+            store.set(&format!("bind_{}", action.name()), self.key_names(action));
+        }
+        store.set("fullscreen", self.fullscreen.to_string());
+        let (w, h) = self.window_size;
+        store.set("window_size", format!("{w}x{h}"));
+        store.set("screen_shake", self.screen_shake.to_string());
+        store.set("game_speed", self.game_speed.to_string());
+        store.flush();
+    }
+    /// Names of every key bound to `action`, separated by commas
+    pub fn key_names(&self, action: InputAction) -> String {
+        self.bindings
+            .iter()
+            .filter(|(bound, _)| *bound == action)
+            .map(|(_, key)| key_name(*key))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// The variant of `all` called `value`, ignoring case
+fn parse_named<T: Copy>(
+    value: Option<String>,
+    all: &[T],
+    name: fn(T) -> &'static str,
+) -> Option<T> {
+    let value = value?;
+    all.iter()
+        .copied()
+        .find(|f| name(*f).eq_ignore_ascii_case(&value))
+}
+
+/// Somewhere settings are kept between runs, as text under string keys
+pub trait SettingsStore {
+    fn get(&self, key: &str) -> Option<String>;
+    fn set(&mut self, key: &str, value: String);
+    /// Makes sure everything set so far is kept
+    fn flush(&mut self);
+}
+
+/// Keeps settings for as long as the game runs, and holds them for the stores that save them
+#[derive(Default)]
+pub struct MemoryStore {
+    values: Vec<(String, String)>,
+}
+impl SettingsStore for MemoryStore {
+    fn get(&self, key: &str) -> Option<String> {
+        self.values
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.clone())
+    }
+    fn set(&mut self, key: &str, value: String) {
+        match self.values.iter_mut().find(|(name, _)| name == key) {
+            Some((_, old)) => *old = value,
+            None => self.values.push((key.to_string(), value)),
+        }
+    }
+    fn flush(&mut self) {}
+}
+
+/// Reads `key = value` lines, skipping blank lines, `#` comments and anything else that doesn't fit
+pub fn parse_config(text: &str) -> MemoryStore {
+    let mut store = MemoryStore::default();
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('#') {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            store.set(key.trim(), value.trim().to_string());
+        }
+    }
+    store
+}
+
+/// Writes settings back out as `key = value` lines
+pub fn format_config(store: &MemoryStore) -> String {
+    let mut text = String::from("# winternight settings\n");
+    for (key, value) in &store.values {
+        text.push_str(&format!("{key} = {value}\n"));
+    }
+    text
+}
+
+/// A settings file in the user's config folder, for desktop builds
+#[cfg(not(target_arch = "wasm32"))]
+pub struct ConfigFile {
+    path: std::path::PathBuf,
+    values: MemoryStore,
+}
+#[cfg(not(target_arch = "wasm32"))]
+impl ConfigFile {
+    /// Opens the settings file at the usual place, or starts a new one if there isn't one yet
+    pub fn open() -> Self {
+        let path = config_dir().join("winternight").join("settings.cfg");
+        let text = std::fs::read_to_string(&path).unwrap_or_default();
+        Self {
+            path,
+            values: parse_config(&text),
+        }
+    }
+}
+#[cfg(not(target_arch = "wasm32"))]
+impl SettingsStore for ConfigFile {
+    fn get(&self, key: &str) -> Option<String> {
+        self.values.get(key)
+    }
+    fn set(&mut self, key: &str, value: String) {
+        self.values.set(key, value);
+    }
+    fn flush(&mut self) {
+        // losing settings isn't worth crashing over
+        let result = self
+            .path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&self.path, format_config(&self.values)));
+        if let Err(err) = result {
+            macroquad::logging::error!("couldn't save settings to {}: {err}", self.path.display());
+        }
+    }
+}
+
+/// Settings in the browser's local storage, for the web build. web/settings.js does the storing,
+/// as the same text as a settings file.
+#[cfg(target_arch = "wasm32")]
+pub struct LocalStorage {
+    values: MemoryStore,
+}
+#[cfg(target_arch = "wasm32")]
+unsafe extern "C" {
+    /// Bytes of the saved settings, 0 if there aren't any
+    fn settings_length() -> u32;
+    /// Copies the saved settings to `ptr`, which has room for `settings_length` bytes
+    fn settings_read(ptr: *mut u8);
+    fn settings_write(ptr: *const u8, len: u32);
+}
+/// Lets the page check it has the right version of web/settings.js
+#[cfg(target_arch = "wasm32")]
+#[unsafe(no_mangle)]
+pub extern "C" fn winternight_settings_crate_version() -> u32 {
+    1
+}
+#[cfg(target_arch = "wasm32")]
+impl LocalStorage {
+    pub fn open() -> Self {
+        let mut bytes = vec![0; unsafe { settings_length() } as usize];
+        unsafe { settings_read(bytes.as_mut_ptr()) };
+        Self {
+            values: parse_config(&String::from_utf8_lossy(&bytes)),
+        }
+    }
+}
+#[cfg(target_arch = "wasm32")]
+impl SettingsStore for LocalStorage {
+    fn get(&self, key: &str) -> Option<String> {
+        self.values.get(key)
+    }
+    fn set(&mut self, key: &str, value: String) {
+        self.values.set(key, value);
+    }
+    fn flush(&mut self) {
+        let text = format_config(&self.values);
+        unsafe { settings_write(text.as_ptr(), text.len() as u32) };
+    }
+}
+
+/// Where settings go on this platform, falling back to the working directory
+#[cfg(not(target_arch = "wasm32"))]
+fn config_dir() -> std::path::PathBuf {
+    let var = |name: &str| std::env::var_os(name).filter(|f| !f.is_empty());
+    if cfg!(windows) {
+        var("APPDATA").map(Into::into)
+    } else if cfg!(target_os = "macos") {
+        var("HOME").map(|home| std::path::Path::new(&home).join("Library/Application Support"))
+    } else {
+        var("XDG_CONFIG_HOME")
+            .map(Into::into)
+            .or_else(|| var("HOME").map(|home| std::path::Path::new(&home).join(".config")))
+    }
+    .unwrap_or_else(|| ".".into())
+}
+
+/// Where settings are kept on this platform
+pub fn settings_store() -> Box<dyn SettingsStore> {
+    #[cfg(not(target_arch = "wasm32"))]
+    return Box::new(ConfigFile::open());
+    #[cfg(target_arch = "wasm32")]
+    return Box::new(LocalStorage::open());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_settings_load_back() {
+        let settings = Settings {
+            sfx_volume: 0.25,
+            text_speed: TextSpeed::Fast,
+            screen_shake: false,
+            ..Settings::default()
+        };
+        let mut store = MemoryStore::default();
+        settings.save(&mut store);
+        assert_eq!(Settings::load(&store), settings);
+    }
+
+    #[test]
+    fn other_versions_keep_what_they_can() {
+        for version in ["0", "2", "soon"] {
+            let store = parse_config(&format!(
+                "version = {version}\nsfx_volume = 0.25\nold_setting = 3\n"
+            ));
+            let settings = Settings::load(&store);
+            assert_eq!(settings.sfx_volume, 0.25);
+            assert_eq!(settings.music_volume, Settings::default().music_volume);
+        }
+    }
+
+    #[test]
+    fn nonsense_falls_back_to_defaults() {
+        let store = parse_config(
+            "sfx_volume = loud
+            music_volume = NaN
+            game_speed = inf
+            scale_mode = sideways
+            fullscreen = maybe
+            window_size = 0x0
+            bind_interact = Hyper, ???
+            bind_move_up = w, Banana",
+        );
+        let defaults = Settings::default();
+        let settings = Settings::load(&store);
+        assert_eq!(settings.sfx_volume, defaults.sfx_volume);
+        assert_eq!(settings.music_volume, defaults.music_volume);
+        assert_eq!(settings.game_speed, defaults.game_speed);
+        assert_eq!(settings.scale_mode, defaults.scale_mode);
+        assert_eq!(settings.fullscreen, defaults.fullscreen);
+        assert_eq!(settings.window_size, defaults.window_size);
+        // an action left with no keys gets its defaults, and any key that does make sense stays
+        assert_eq!(settings.key_names(InputAction::Interact), "E");
+        assert_eq!(settings.key_names(InputAction::MoveUp), "W");
+    }
+
+    #[test]
+    fn out_of_range_is_clamped() {
+        let store = parse_config("sfx_volume = 3\nmusic_volume = -1\ngame_speed = 0.1\n");
+        let settings = Settings::load(&store);
+        assert_eq!(settings.sfx_volume, 1.0);
+        assert_eq!(settings.music_volume, 0.0);
+        assert_eq!(settings.game_speed, GAME_SPEED_RANGE.0);
+    }
+
+    #[test]
+    fn no_version_is_read_as_this_one() {
+        let store = parse_config("# typed out by hand\nsfx_volume = 0.25\n");
+        assert_eq!(Settings::load(&store).sfx_volume, 0.25);
+    }
+}
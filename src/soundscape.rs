@@ -0,0 +1,214 @@
+use crate::{
+    assets::{Map, TileMap},
+    manifest::{LAYERS, LOOPS},
+    player::Tag,
+};
+
+/// How quickly layers fade in and out, in full volume per second
+const FADE_SPEED: f32 = 0.5;
+/// Every wall between the player and a zone multiplies how loud it is by this
+const WALL_DAMPING: f32 = 0.5;
+
+/// Where a layer can be heard
+#[derive(Clone, Copy)]
+pub enum LayerSource {
+    Everywhere,
+    /// Around every tile of the special layer with this id, fading out to silence `radius` tiles away
+    Zone {
+        special_tile: u8,
+        radius: f32,
+    },
+}
+
+/// A looping sound from `LOOPS`, and when it should be heard
+#[derive(Clone, Copy)]
+pub struct Layer {
+    pub sound: &'static str,
+    pub source: LayerSource,
+    /// Only heard once the player has this tag
+    pub after: Option<Tag>,
+    /// Not heard anymore once the player has this tag
+    pub until: Option<Tag>,
+    /// Gets louder and quieter with the storm
+    pub follows_storm: bool,
+    pub volume: f32,
+}
+
+/// Volumes of every layer in `LAYERS`, fading towards what the story and where the player stands call for
+pub struct Soundscape {
+    /// Tile positions of each layer's zones
+    zones: Vec<Vec<(usize, usize)>>,
+    pub volumes: Vec<f32>,
+}
+impl Soundscape {
+    pub fn new(map: &Map) -> Self {
+        Self {
+            zones: LAYERS
+                .iter()
+                .map(|layer| {
+                    assert!(
+                        LOOPS.iter().any(|(name, _)| *name == layer.sound),
+                        "no loop named {:?}",
+                        layer.sound
+                    );
+                    match layer.source {
+                        LayerSource::Everywhere => Vec::new(),
+                        LayerSource::Zone { special_tile, .. } => {
+                            map.special.find_tiles(special_tile)
+                        }
+                    }
+                })
+                .collect(),
+            volumes: vec![0.0; LAYERS.len()],
+        }
+    }
+    /// Fades every layer towards its volume for the player standing on `listener`
+    pub fn update(
+        &mut self,
+        tags: &[Tag],
+        listener: (usize, usize),
+        walls: &TileMap,
+        storm: f32,
+        delta_time: f32,
+    ) {
+        for ((layer, zones), volume) in LAYERS.iter().zip(&self.zones).zip(&mut self.volumes) {
+            let target = target_volume(layer, zones, tags, listener, walls, storm);
+            let step = FADE_SPEED * delta_time;
+            *volume += (target - *volume).clamp(-step, step);
+        }
+    }
+    /// Volume of every looping sound, the loudest of its layers
+    pub fn sound_volumes(&self) -> Vec<(&'static str, f32)> {
+        let mut sounds: Vec<(&'static str, f32)> = Vec::new();
+        for (layer, volume) in LAYERS.iter().zip(&self.volumes) {
+            match sounds.iter_mut().find(|(sound, _)| *sound == layer.sound) {
+                Some((_, loudest)) => *loudest = loudest.max(*volume),
+                None => sounds.push((layer.sound, *volume)),
+            }
+        }
+        sounds
+    }
+}
+
+/// Volume a layer should be at with these tags, from 0 to the layer's own volume
+pub fn target_volume(
+    layer: &Layer,
+    zones: &[(usize, usize)],
+    tags: &[Tag],
+    listener: (usize, usize),
+    walls: &TileMap,
+    storm: f32,
+) -> f32 {
+    let started = layer.after.is_none_or(|tag| tags.contains(&tag));
+    let stopped = layer.until.is_some_and(|tag| tags.contains(&tag));
+    if !started || stopped {
+        return 0.0;
+    }
+    let nearness = match layer.source {
+        LayerSource::Everywhere => 1.0,
+        LayerSource::Zone { radius, .. } => zones
+            .iter()
+            .map(|zone| zone_volume(*zone, listener, radius, walls))
+            .fold(0.0, f32::max),
+    };
+    let storm = if layer.follows_storm { storm } else { 1.0 };
+    layer.volume * nearness * storm
+}
+
+/// How loud a zone on `zone` sounds from `listener`, from 0 to 1.
+/// Drops off to nothing `radius` tiles away, and a bit more for every wall in between.
+pub fn zone_volume(
+    zone: (usize, usize),
+    listener: (usize, usize),
+    radius: f32,
+    walls: &TileMap,
+) -> f32 {
+    let distance = (zone.0 as f32 - listener.0 as f32).hypot(zone.1 as f32 - listener.1 as f32);
+    let falloff = (1.0 - distance / radius).max(0.0);
+    falloff * WALL_DAMPING.powi(walls_between(zone, listener, walls) as i32)
+}
+
+/// Wall tiles crossed by a straight line between two tiles, not counting the tiles at either end
+pub fn walls_between(from: (usize, usize), to: (usize, usize), walls: &TileMap) -> usize {
+    let (dx, dy) = (to.0 as f32 - from.0 as f32, to.1 as f32 - from.1 as f32);
+    // a couple of samples per tile, so diagonal lines don't skip over corners
+    let steps = (dx.abs().max(dy.abs()) * 2.0).ceil() as usize;
+    let mut crossed: Vec<(usize, usize)> = Vec::new();
+    for step in 1..steps {
+        let progress = step as f32 / steps as f32;
+        let tile = (
+            (from.0 as f32 + dx * progress).round() as usize,
+            (from.1 as f32 + dy * progress).round() as usize,
+        );
+        if tile != from && tile != to && !crossed.contains(&tile) {
+            crossed.push(tile);
+        }
+    }
+    crossed
+        .iter()
+        .filter(|(x, y)| walls.0[x + y * walls.1] != 0)
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KETTLE: Layer = Layer {
+        sound: "kettle",
+        source: LayerSource::Zone {
+            special_tile: 7,
+            radius: 8.0,
+        },
+        after: Some(Tag::ChildrenWantChocolate),
+        until: Some(Tag::CarryingCups),
+        follows_storm: false,
+        volume: 0.8,
+    };
+
+    #[test]
+    fn fades_out_by_radius() {
+        let open = TileMap::from_rows(&["..."; 12]);
+        assert_eq!(zone_volume((1, 1), (1, 1), 8.0, &open), 1.0);
+        assert_eq!(zone_volume((1, 1), (1, 5), 8.0, &open), 0.5);
+        assert_eq!(zone_volume((1, 1), (1, 9), 8.0, &open), 0.0);
+        assert_eq!(zone_volume((1, 1), (1, 11), 8.0, &open), 0.0);
+    }
+
+    #[test]
+    fn every_wall_halves_it() {
+        let walls = TileMap::from_rows(&[
+            "...", "...", "###", "...", "###", "...", "...", "...", "...",
+        ]);
+        assert_eq!(walls_between((1, 0), (1, 1), &walls), 0);
+        assert_eq!(walls_between((1, 0), (1, 3), &walls), 1);
+        assert_eq!(walls_between((1, 0), (1, 5), &walls), 2);
+        // 4 tiles away out of 8, through one wall then two
+        assert_eq!(zone_volume((1, 0), (1, 4), 8.0, &walls), 0.5 * 0.5);
+        assert_eq!(zone_volume((1, 1), (1, 5), 8.0, &walls), 0.5 * 0.25);
+        // the walls a zone or listener stands in don't count
+        assert_eq!(walls_between((1, 2), (1, 4), &walls), 0);
+    }
+
+    #[test]
+    fn tags_start_and_stop_layers() {
+        let open = TileMap::from_rows(&["..."; 3]);
+        let volume = |tags: &[Tag]| target_volume(&KETTLE, &[(1, 1)], tags, (1, 1), &open, 0.3);
+        assert_eq!(volume(&[]), 0.0);
+        assert_eq!(volume(&[Tag::ChildrenWantChocolate]), 0.8);
+        assert_eq!(
+            volume(&[Tag::ChildrenWantChocolate, Tag::CarryingCups]),
+            0.0
+        );
+        // the storm only counts for layers that follow it
+        let wind = Layer {
+            source: LayerSource::Everywhere,
+            after: None,
+            until: None,
+            follows_storm: true,
+            volume: 1.0,
+            ..KETTLE
+        };
+        assert_eq!(target_volume(&wind, &[], &[], (1, 1), &open, 0.3), 0.3);
+    }
+}
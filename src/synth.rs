@@ -0,0 +1,229 @@
+use std::f32::consts::TAU;
+
+use crate::rng::Rng;
+
+/// Samples per second of every synthesized sound
+pub const SAMPLE_RATE: u32 = 22050;
+/// How fast the pitch of a sound with vibrato wavers, in hertz
+const VIBRATO_RATE: f32 = 23.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Waveform {
+    Sine,
+    Square,
+    Triangle,
+    Saw,
+    /// White noise, ignoring pitch
+    Noise,
+}
+impl Waveform {
+    /// Value of the wave at `phase`, in cycles, from -1 to 1
+    pub fn sample(self, phase: f32, rng: &mut Rng) -> f32 {
+        let phase = phase.fract();
+        match self {
+            Waveform::Sine => (phase * TAU).sin(),
+            Waveform::Square => {
+                if phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+            Waveform::Saw => phase * 2.0 - 1.0,
+            Waveform::Noise => rng.next_f32() * 2.0 - 1.0,
+        }
+    }
+}
+
+/// A short sound effect, described well enough to be synthesized at load
+#[derive(Clone, Copy)]
+pub struct SoundEffect {
+    pub waveform: Waveform,
+    /// Pitch at the start and at the end, in hertz
+    pub pitch: (f32, f32),
+    /// Seconds to fade in, then to fade out again
+    pub attack: f32,
+    pub decay: f32,
+    /// How much the pitch wavers, as a fraction of it. Makes creaks creak.
+    pub vibrato: f32,
+    /// Smooths out the wave, from 0 (harsh) to just under 1 (very muffled)
+    pub muffle: f32,
+    pub volume: f32,
+    /// Times the sound plays back to back, and seconds from the start of one to the next
+    pub repeats: usize,
+    pub spacing: f32,
+}
+impl SoundEffect {
+    /// Seconds from the start of the first repeat to the end of the last
+    pub fn length(&self) -> f32 {
+        self.spacing * self.repeats.saturating_sub(1) as f32 + self.attack + self.decay
+    }
+    /// Volume `time` seconds into one repeat, from 0 to 1
+    fn envelope(&self, time: f32) -> f32 {
+        if time < self.attack {
+            time / self.attack
+        } else {
+            (1.0 - (time - self.attack) / self.decay).max(0.0)
+        }
+    }
+    /// Synthesizes the sound into samples from -1 to 1. The same seed always gives the same samples.
+    pub fn render(&self, seed: u64) -> Vec<f32> {
+        let mut rng = Rng::new(seed);
+        let count = (self.length() * SAMPLE_RATE as f32) as usize;
+        let hit_length = self.attack + self.decay;
+        let mut samples = Vec::with_capacity(count);
+        let mut phase = 0.0;
+        let mut smoothed = 0.0;
+        for i in 0..count {
+            let time = i as f32 / SAMPLE_RATE as f32;
+            // only the latest repeat sounds, the earlier ones have faded by then
+            let repeat = if self.spacing > 0.0 {
+                ((time / self.spacing) as usize).min(self.repeats.saturating_sub(1))
+            } else {
+                0
+            };
+            let hit_time = time - repeat as f32 * self.spacing;
+            let progress = (hit_time / hit_length).min(1.0);
+            let pitch = self.pitch.0 + (self.pitch.1 - self.pitch.0) * progress;
+            let pitch = pitch * (1.0 + self.vibrato * (time * VIBRATO_RATE * TAU).sin());
+            phase += pitch / SAMPLE_RATE as f32;
+            let raw = self.waveform.sample(phase, &mut rng);
+            smoothed += (raw - smoothed) * (1.0 - self.muffle);
+            samples.push(smoothed * self.envelope(hit_time) * self.volume);
+        }
+        samples
+    }
+}
+
+/// Packs samples from -1 to 1 into a mono 16 bit wav file
+pub fn encode_wav(samples: &[f32]) -> Vec<u8> {
+    let data_size = samples.len() as u32 * 2;
+    let mut wav = Vec::with_capacity(44 + data_size as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_size).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    // plain pcm, one channel
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    wav.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_size.to_le_bytes());
+    for sample in samples {
+        let sample = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        wav.extend_from_slice(&sample.to_le_bytes());
+    }
+    wav
+}
+
+/// Notes played one after another, over and over
+#[derive(Clone, Copy)]
+pub struct Melody {
+    pub waveform: Waveform,
+    /// Semitones above A4, or None for a rest, and how many beats each lasts
+    pub notes: &'static [(Option<i32>, f32)],
+    pub beats_per_minute: f32,
+    /// Notes fade out over their length like a plucked string, instead of holding
+    pub pluck: bool,
+    pub vibrato: f32,
+    pub muffle: f32,
+    pub volume: f32,
+}
+
+/// Noise that swells and dies down, with the odd pop, over and over
+#[derive(Clone, Copy)]
+pub struct Ambience {
+    /// Length of one loop
+    pub seconds: f32,
+    pub muffle: f32,
+    /// Times the noise swells up and dies down again every loop
+    pub swells: usize,
+    /// Pops a second on average, for crackling
+    pub pops: f32,
+    pub volume: f32,
+}
+
+/// A sound that plays on repeat, faded in and out by the soundscape
+#[derive(Clone, Copy)]
+pub enum LoopSound {
+    Melody(Melody),
+    Ambience(Ambience),
+}
+impl LoopSound {
+    /// Synthesizes one loop into samples from -1 to 1, starting and ending smoothly so it repeats without a click
+    pub fn render(&self, seed: u64) -> Vec<f32> {
+        match self {
+            LoopSound::Melody(melody) => melody.render(seed),
+            LoopSound::Ambience(ambience) => ambience.render(seed),
+        }
+    }
+}
+
+/// Seconds a note takes to fade in, and a held note to fade out
+const NOTE_FADE: f32 = 0.02;
+
+impl Melody {
+    fn render(&self, seed: u64) -> Vec<f32> {
+        let seconds_per_beat = 60.0 / self.beats_per_minute;
+        let mut rng = Rng::new(seed);
+        let mut samples = Vec::new();
+        let mut smoothed = 0.0;
+        for (note, beats) in self.notes {
+            let count = (beats * seconds_per_beat * SAMPLE_RATE as f32) as usize;
+            let length = count as f32 / SAMPLE_RATE as f32;
+            let mut phase = 0.0;
+            for i in 0..count {
+                let time = i as f32 / SAMPLE_RATE as f32;
+                let raw = match note {
+                    Some(semitones) => {
+                        let pitch = 440.0 * 2f32.powf(*semitones as f32 / 12.0);
+                        let pitch =
+                            pitch * (1.0 + self.vibrato * (time * VIBRATO_RATE * TAU).sin());
+                        phase += pitch / SAMPLE_RATE as f32;
+                        let fade_in = (time / NOTE_FADE).min(1.0);
+                        let fade_out = if self.pluck {
+                            1.0 - time / length
+                        } else {
+                            ((length - time) / NOTE_FADE).min(1.0)
+                        };
+                        self.waveform.sample(phase, &mut rng) * fade_in * fade_out
+                    }
+                    None => 0.0,
+                };
+                smoothed += (raw - smoothed) * (1.0 - self.muffle);
+                samples.push(smoothed * self.volume);
+            }
+        }
+        samples
+    }
+}
+
+impl Ambience {
+    fn render(&self, seed: u64) -> Vec<f32> {
+        let count = (self.seconds * SAMPLE_RATE as f32) as usize;
+        let mut rng = Rng::new(seed);
+        let mut samples = Vec::with_capacity(count);
+        let (mut smoothed, mut pop) = (0.0, 0.0);
+        for i in 0..count {
+            let raw = rng.next_f32() * 2.0 - 1.0;
+            smoothed += (raw - smoothed) * (1.0 - self.muffle);
+            // whole swells fit in a loop, so the end flows back into the start
+            let swell = if self.swells > 0 {
+                let progress = i as f32 / count as f32;
+                0.6 + 0.4 * (progress * self.swells as f32 * TAU).sin()
+            } else {
+                1.0
+            };
+            if rng.next_f32() < self.pops / SAMPLE_RATE as f32 {
+                pop = rng.range((0.4, 1.0));
+            }
+            pop *= 0.996;
+            samples.push((smoothed * swell + raw * pop * 0.5) * self.volume);
+        }
+        samples
+    }
+}
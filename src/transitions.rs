@@ -0,0 +1,327 @@
+use macroquad::prelude::*;
+
+use crate::{
+    player::Direction,
+    utils::{FADE_TIME, SCREEN_HEIGHT, SCREEN_WIDTH},
+};
+
+/// Size of the squares a dissolve fills in, in pixels
+const DISSOLVE_CELL: usize = 4;
+// 4x4 ordered dither thresholds, so a dissolve fills in evenly instead of in clumps
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+/// Triangles used to draw the edge of an iris
+const IRIS_SEGMENTS: usize = 48;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+impl Easing {
+    /// Maps progress from 0 to 1 onto an eased value from 0 to 1
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::EaseInOut => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    1.0 - 2.0 * (1.0 - t) * (1.0 - t)
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum TransitionKind {
+    /// The whole screen fades to the colour
+    Fade,
+    /// A circle closes in on the middle of the screen
+    Iris,
+    /// The colour fills in square by square
+    Dissolve,
+    /// A panel of the colour slides across the screen in this direction
+    Slide(Direction),
+}
+
+/// Covers the screen and uncovers it again, switching what's shown halfway through
+#[derive(Clone, Copy)]
+pub struct Transition {
+    pub kind: TransitionKind,
+    pub color: Color,
+    /// Seconds for covering and uncovering together
+    pub duration: f32,
+    /// Easing of each half, going from uncovered to covered
+    pub easing: Easing,
+}
+impl Transition {
+    /// Whether the switch has happened `time` seconds in
+    pub fn is_switched(&self, time: f32) -> bool {
+        time >= self.duration / 2.0
+    }
+    pub fn is_finished(&self, time: f32) -> bool {
+        time >= self.duration
+    }
+    /// How much of the screen is covered `time` seconds in, from 0 to 1
+    pub fn coverage(&self, time: f32) -> f32 {
+        if self.duration <= 0.0 {
+            return 0.0;
+        }
+        let half = self.duration / 2.0;
+        let t = if time < half {
+            time / half
+        } else {
+            (self.duration - time) / half
+        };
+        self.easing.apply(t)
+    }
+    /// Draws the transition `time` seconds in over the whole frame
+    pub fn draw(&self, time: f32) {
+        let coverage = self.coverage(time);
+        if coverage <= 0.0 {
+            return;
+        }
+        match self.kind {
+            TransitionKind::Fade => draw_rectangle(
+                0.0,
+                0.0,
+                SCREEN_WIDTH,
+                SCREEN_HEIGHT,
+                self.color.with_alpha(self.color.a * coverage),
+            ),
+            TransitionKind::Iris => draw_iris(iris_radius(coverage), self.color),
+            TransitionKind::Dissolve => {
+                for y in 0..SCREEN_HEIGHT as usize / DISSOLVE_CELL {
+                    for x in 0..SCREEN_WIDTH as usize / DISSOLVE_CELL {
+                        if dissolve_covers(x, y, coverage) {
+                            draw_rectangle(
+                                (x * DISSOLVE_CELL) as f32,
+                                (y * DISSOLVE_CELL) as f32,
+                                DISSOLVE_CELL as f32,
+                                DISSOLVE_CELL as f32,
+                                self.color,
+                            );
+                        }
+                    }
+                }
+            }
+            TransitionKind::Slide(direction) => {
+                let rect = slide_rect(direction, coverage, self.is_switched(time));
+                draw_rectangle(rect.x, rect.y, rect.w, rect.h, self.color);
+            }
+        }
+    }
+}
+
+/// Radius of the hole left open by an iris at `coverage`, reaching past the corners when uncovered
+pub fn iris_radius(coverage: f32) -> f32 {
+    vec2(SCREEN_WIDTH, SCREEN_HEIGHT).length() / 2.0 * (1.0 - coverage)
+}
+
+fn draw_iris(radius: f32, color: Color) {
+    let center = vec2(SCREEN_WIDTH, SCREEN_HEIGHT) / 2.0;
+    // anything past the corners is off screen anyway
+    let outer = vec2(SCREEN_WIDTH, SCREEN_HEIGHT).length();
+    let angle = |i: usize| i as f32 / IRIS_SEGMENTS as f32 * std::f32::consts::TAU;
+    for i in 0..IRIS_SEGMENTS {
+        let (a, b) = (Vec2::from_angle(angle(i)), Vec2::from_angle(angle(i + 1)));
+        let (inner_a, inner_b) = (center + a * radius, center + b * radius);
+        let (outer_a, outer_b) = (center + a * outer, center + b * outer);
+        draw_triangle(inner_a, outer_a, outer_b, color);
+        draw_triangle(inner_a, outer_b, inner_b, color);
+    }
+}
+
+/// Whether the dissolve cell at (x, y) is filled in at `coverage`
+pub fn dissolve_covers(x: usize, y: usize, coverage: f32) -> bool {
+    (BAYER[y % 4][x % 4] as f32 + 0.5) / 16.0 < coverage
+}
+
+/// Part of the screen a slide covers. Before the switch the panel comes in from behind,
+/// after it the panel carries on out the other side.
+pub fn slide_rect(direction: Direction, coverage: f32, switched: bool) -> Rect {
+    let (w, h) = (SCREEN_WIDTH, SCREEN_HEIGHT);
+    let dir = direction.to_vec2();
+    // which edge the panel hugs: the one it comes in from, then the one it leaves through
+    let from_far_edge = (dir.x < 0.0 || dir.y < 0.0) != switched;
+    if dir.x != 0.0 {
+        let size = w * coverage;
+        let x = if from_far_edge { w - size } else { 0.0 };
+        Rect::new(x, 0.0, size, h)
+    } else {
+        let size = h * coverage;
+        let y = if from_far_edge { h - size } else { 0.0 };
+        Rect::new(0.0, y, w, size)
+    }
+}
+
+/// The slow fade to black the game has always used
+pub const FADE: Transition = Transition {
+    kind: TransitionKind::Fade,
+    color: BLACK,
+    duration: FADE_TIME,
+    easing: Easing::EaseOut,
+};
+pub const IRIS: Transition = Transition {
+    kind: TransitionKind::Iris,
+    color: BLACK,
+    duration: 1.2,
+    easing: Easing::EaseInOut,
+};
+pub const DISSOLVE: Transition = Transition {
+    kind: TransitionKind::Dissolve,
+    color: BLACK,
+    duration: 0.8,
+    easing: Easing::Linear,
+};
+pub const SLIDE: Transition = Transition {
+    kind: TransitionKind::Slide(Direction::Left),
+    color: BLACK,
+    duration: 0.8,
+    easing: Easing::EaseIn,
+};
+
+/// A transition that's playing, and what to switch to once the screen is covered
+pub struct ActiveTransition<T> {
+    pub transition: Transition,
+    pub time: f32,
+    pub target: T,
+    pub switched: bool,
+}
+impl<T> ActiveTransition<T> {
+    pub fn new(transition: Transition, target: T) -> Self {
+        Self {
+            transition,
+            time: 0.0,
+            target,
+            switched: false,
+        }
+    }
+    /// Starts already covered, so the switch happens right away and it only uncovers
+    pub fn covered(transition: Transition, target: T) -> Self {
+        Self {
+            time: transition.duration / 2.0,
+            ..Self::new(transition, target)
+        }
+    }
+    /// Steps the transition forward, returning true on the frame it switches
+    pub fn update(&mut self, delta_time: f32) -> bool {
+        self.time += delta_time;
+        if !self.switched && self.transition.is_switched(self.time) {
+            self.switched = true;
+            return true;
+        }
+        false
+    }
+    pub fn draw(&self) {
+        self.transition.draw(self.time);
+    }
+    pub fn is_finished(&self) -> bool {
+        self.transition.is_finished(self.time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EASINGS: [Easing; 4] = [
+        Easing::Linear,
+        Easing::EaseIn,
+        Easing::EaseOut,
+        Easing::EaseInOut,
+    ];
+    const STEPS: usize = 100;
+
+    fn progress() -> impl Iterator<Item = f32> {
+        (0..=STEPS).map(|f| f as f32 / STEPS as f32)
+    }
+
+    fn transition(kind: TransitionKind, easing: Easing) -> Transition {
+        Transition {
+            kind,
+            color: BLACK,
+            duration: 2.0,
+            easing,
+        }
+    }
+
+    #[test]
+    fn easings_start_at_zero_and_end_at_one() {
+        for easing in EASINGS {
+            assert_eq!(easing.apply(0.0), 0.0);
+            assert_eq!(easing.apply(1.0), 1.0);
+            let values: Vec<f32> = progress().map(|t| easing.apply(t)).collect();
+            assert!(values.windows(2).all(|f| f[0] <= f[1]), "{easing:?}");
+        }
+    }
+
+    #[test]
+    fn coverage_rises_then_falls() {
+        for easing in EASINGS {
+            let fade = transition(TransitionKind::Fade, easing);
+            assert_eq!(fade.coverage(0.0), 0.0);
+            assert_eq!(fade.coverage(1.0), 1.0);
+            assert_eq!(fade.coverage(2.0), 0.0);
+            let covering: Vec<f32> = progress().map(|t| fade.coverage(t)).collect();
+            let uncovering: Vec<f32> = progress().map(|t| fade.coverage(1.0 + t)).collect();
+            assert!(covering.windows(2).all(|f| f[0] <= f[1]));
+            assert!(uncovering.windows(2).all(|f| f[0] >= f[1]));
+        }
+        assert!(!transition(TransitionKind::Fade, Easing::Linear).is_switched(0.99));
+        assert!(transition(TransitionKind::Fade, Easing::Linear).is_switched(1.0));
+    }
+
+    #[test]
+    fn slides_grow_from_nothing_to_the_whole_screen() {
+        let screen = Rect::new(0.0, 0.0, SCREEN_WIDTH, SCREEN_HEIGHT);
+        for direction in [
+            Direction::Left,
+            Direction::Right,
+            Direction::Up,
+            Direction::Down,
+        ] {
+            for switched in [false, true] {
+                let empty = slide_rect(direction, 0.0, switched);
+                assert_eq!(empty.w * empty.h, 0.0);
+                assert_eq!(slide_rect(direction, 1.0, switched), screen);
+                let areas: Vec<f32> = progress()
+                    .map(|f| slide_rect(direction, f, switched))
+                    .map(|f| f.w * f.h)
+                    .collect();
+                assert!(areas.windows(2).all(|f| f[0] <= f[1]));
+            }
+        }
+    }
+
+    #[test]
+    fn dissolve_fills_in_every_cell() {
+        // the frame divides evenly into cells, so covering every cell covers every pixel
+        assert_eq!(SCREEN_WIDTH as usize % DISSOLVE_CELL, 0);
+        assert_eq!(SCREEN_HEIGHT as usize % DISSOLVE_CELL, 0);
+        let w = SCREEN_WIDTH as usize / DISSOLVE_CELL;
+        let h = SCREEN_HEIGHT as usize / DISSOLVE_CELL;
+        let covered = |coverage| {
+            (0..w * h)
+                .filter(|f| dissolve_covers(f % w, f / w, coverage))
+                .count()
+        };
+        assert_eq!(covered(0.0), 0);
+        assert_eq!(covered(1.0), w * h);
+        let counts: Vec<usize> = progress().map(covered).collect();
+        assert!(counts.windows(2).all(|f| f[0] <= f[1]));
+    }
+
+    #[test]
+    fn iris_closes_completely() {
+        assert_eq!(iris_radius(1.0), 0.0);
+        let corner = vec2(SCREEN_WIDTH, SCREEN_HEIGHT) / 2.0;
+        assert!(iris_radius(0.0) >= corner.length());
+    }
+}
@@ -5,6 +5,9 @@ pub const SCREEN_HEIGHT: f32 = 144.0 * 2.0;
 
 pub const FADE_TIME: f32 = 3.0;
 
+/// Point of a tile that sprites are anchored to, the middle of its bottom edge
+pub const TILE_ANCHOR: Vec2 = Vec2::new(8.0, 16.0);
+
 pub fn create_camera(w: f32, h: f32) -> Camera2D {
     let rt = render_target(w as u32, h as u32);
     rt.texture.set_filter(FilterMode::Nearest);
@@ -15,19 +18,3 @@ pub fn create_camera(w: f32, h: f32) -> Camera2D {
         ..Default::default()
     }
 }
-pub fn get_input_axis() -> Vec2 {
-    let mut i = Vec2::ZERO;
-    if is_key_down(KeyCode::A) || is_key_down(KeyCode::Left) {
-        i.x -= 1.0;
-    }
-    if is_key_down(KeyCode::D) || is_key_down(KeyCode::Right) {
-        i.x += 1.0;
-    }
-    if is_key_down(KeyCode::W) || is_key_down(KeyCode::Up) {
-        i.y -= 1.0;
-    }
-    if is_key_down(KeyCode::S) || is_key_down(KeyCode::Down) {
-        i.y += 1.0;
-    }
-    i
-}
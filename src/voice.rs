@@ -0,0 +1,197 @@
+use crate::{
+    audio::Audio,
+    manifest::VOICES,
+    settings::TextSpeed,
+    synth::{SoundEffect, Waveform},
+};
+
+/// Different pitches each voice has blips for
+pub const BLIP_VARIANTS: usize = 5;
+/// Only every this many letters gets a blip, any more and it turns into a buzz
+const BLIP_EVERY: usize = 2;
+/// Characters revealed a second for speakers without a voice
+const SILENT_SPEED: f32 = 30.0;
+
+/// How a speaker sounds, blipping along as their dialogue is revealed
+#[derive(Clone, Copy)]
+#[cfg_attr(not(feature = "audio"), allow(dead_code))]
+pub struct Voice {
+    pub waveform: Waveform,
+    /// Pitch of the middle blip, in hertz
+    pub pitch: f32,
+    /// Semitones the blips spread over, wider for livelier voices
+    pub range: f32,
+    /// What each blip's pitch slides to by its end, as a fraction of where it started
+    pub slide: f32,
+    pub muffle: f32,
+    /// Seconds each blip lasts
+    pub blip_length: f32,
+    /// Characters revealed a second
+    pub speed: f32,
+}
+#[cfg_attr(not(feature = "audio"), allow(dead_code))]
+impl Voice {
+    /// Samples of one blip, `variant` picking its pitch within the voice's range.
+    /// The same voice and variant always give the same samples.
+    pub fn blip(&self, variant: usize) -> Vec<f32> {
+        let pitch = self.blip_pitch(variant);
+        SoundEffect {
+            waveform: self.waveform,
+            pitch: (pitch, pitch * self.slide),
+            attack: 0.005,
+            decay: self.blip_length,
+            vibrato: 0.0,
+            muffle: self.muffle,
+            volume: 0.3,
+            repeats: 1,
+            spacing: 0.0,
+        }
+        .render(variant as u64)
+    }
+    /// Pitch a blip starts at, in hertz, spread evenly over the voice's range
+    pub fn blip_pitch(&self, variant: usize) -> f32 {
+        let step = self.range / (BLIP_VARIANTS - 1) as f32;
+        let semitones = variant as f32 * step - self.range / 2.0;
+        self.pitch * 2f32.powf(semitones / 12.0)
+    }
+}
+
+/// Which blip a character gets, the same for the same letter every time
+pub fn blip_variant(c: char) -> usize {
+    (c as usize).wrapping_mul(7) % BLIP_VARIANTS
+}
+
+/// Name and voice of a speaker from `VOICES`, if they have one
+pub fn voice(speaker: &str) -> Option<(&'static str, &'static Voice)> {
+    VOICES
+        .iter()
+        .find(|(name, _)| *name == speaker)
+        .map(|(name, voice)| (*name, voice))
+}
+
+struct Line {
+    text: String,
+    speaker: String,
+    time: f32,
+    /// Characters showing so far
+    revealed: usize,
+    /// Characters voiced so far
+    voiced: usize,
+    /// Whether it was drawn since the last update
+    shown: bool,
+}
+
+/// Reveals dialogue a character at a time, in the speaker's voice
+#[derive(Default)]
+pub struct Typewriter {
+    lines: Vec<Line>,
+}
+impl Typewriter {
+    /// Characters of `text` to show this frame, starting it off if it's new
+    pub fn reveal(&mut self, text: &str, speaker: &str) -> usize {
+        match self.lines.iter_mut().find(|f| f.text == text) {
+            Some(line) => {
+                line.shown = true;
+                line.revealed
+            }
+            None => {
+                self.lines.push(Line {
+                    text: text.to_string(),
+                    speaker: speaker.to_string(),
+                    time: 0.0,
+                    revealed: 0,
+                    voiced: 0,
+                    shown: true,
+                });
+                0
+            }
+        }
+    }
+    /// Shows the rest of `text` straight away, without voicing it
+    pub fn finish(&mut self, text: &str) {
+        if let Some(line) = self.lines.iter_mut().find(|f| f.text == text) {
+            line.revealed = line.text.chars().count();
+            line.voiced = line.revealed;
+            line.time = f32::INFINITY;
+        }
+    }
+    /// Whether every line showing is completely revealed
+    pub fn is_finished(&self) -> bool {
+        self.lines
+            .iter()
+            .all(|f| f.revealed == f.text.chars().count())
+    }
+    /// Reveals more of every line, blipping for new letters, and forgets lines that weren't drawn
+    pub fn update(&mut self, delta_time: f32, text_speed: TextSpeed, audio: &mut Audio) {
+        self.lines.retain(|f| f.shown);
+        let Some(multiplier) = text_speed.multiplier() else {
+            for line in &mut self.lines {
+                line.shown = false;
+                line.revealed = line.text.chars().count();
+                line.voiced = line.revealed;
+            }
+            return;
+        };
+        for line in &mut self.lines {
+            line.shown = false;
+            line.time += delta_time;
+            let voice = voice(&line.speaker);
+            let speed = voice.map_or(SILENT_SPEED, |(_, voice)| voice.speed) * multiplier;
+            line.revealed = ((line.time * speed) as usize).min(line.text.chars().count());
+            if let Some((name, _)) = voice {
+                for (index, c) in line.text.chars().enumerate().take(line.revealed) {
+                    if index >= line.voiced && c.is_alphanumeric() && index % BLIP_EVERY == 0 {
+                        audio.play_blip(name, blip_variant(c));
+                    }
+                }
+            }
+            line.voiced = line.revealed;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::NullAudio;
+
+    const TEXT: &str = "Could you please light the fireplace? I'm so cold.";
+
+    /// Pitches `speaker` blips at while saying `text`, a frame at a time
+    fn pitches(speaker: &str) -> Vec<f32> {
+        let backend = NullAudio;
+        let mut audio = Audio::new(&backend);
+        let mut typewriter = Typewriter::default();
+        let mut pitches = Vec::new();
+        // long enough for the slowest voice to get through all of it
+        for _ in 0..60 {
+            audio.begin_frame();
+            typewriter.reveal(TEXT, speaker);
+            typewriter.update(0.1, TextSpeed::Normal, &mut audio);
+            for (name, variant) in &audio.blips {
+                pitches.push(voice(name).unwrap().1.blip_pitch(*variant));
+            }
+        }
+        pitches
+    }
+
+    #[test]
+    fn same_speaker_same_blips() {
+        assert_eq!(pitches("Ferret Mother"), pitches("Ferret Mother"));
+        let (_, voice) = voice("Child Ferret").unwrap();
+        for variant in 0..BLIP_VARIANTS {
+            assert_eq!(voice.blip(variant), voice.blip(variant));
+        }
+    }
+
+    #[test]
+    fn different_speakers_sound_different() {
+        let mother = pitches("Ferret Mother");
+        let child = pitches("Child Ferret");
+        assert!(!mother.is_empty() && !child.is_empty());
+        assert_ne!(mother, child);
+        let (_, mother) = voice("Ferret Mother").unwrap();
+        let (_, child) = voice("Child Ferret").unwrap();
+        assert_ne!(mother.blip(0), child.blip(0));
+    }
+}
@@ -0,0 +1,274 @@
+use macroquad::prelude::*;
+
+use crate::{
+    assets::Map,
+    characters::{Character, DrawCtx},
+    rng::Rng,
+    utils::{SCREEN_HEIGHT, SCREEN_WIDTH},
+};
+
+/// Snowflakes spawned every second at full intensity
+const SNOW_RATE: f32 = 160.0;
+/// Range of falling speeds of snowflakes, in pixels per second
+const FALL_SPEED: (f32, f32) = (14.0, 32.0);
+/// Range of how long a snowflake drifts before fading out, in seconds
+const SNOW_LIFE: (f32, f32) = (2.0, 4.5);
+/// Sideways wind at full intensity outside of gusts, in pixels per second. Negative blows left.
+const BASE_WIND: f32 = -40.0;
+/// Range of seconds between a gust starting or dying down
+const GUST_INTERVAL: (f32, f32) = (1.5, 5.0);
+/// Range of how much a gust multiplies the wind by, on top of the base wind
+const GUST_STRENGTH: (f32, f32) = (0.5, 2.0);
+/// How quickly the storm eases to a new intensity, in full changes per second
+const INTENSITY_SPEED: f32 = 0.1;
+/// Steam puffs spawned every second from each source
+const STEAM_RATE: f32 = 7.0;
+const STEAM_LIFE: (f32, f32) = (1.0, 1.8);
+/// Seconds out in full intensity snow until a coat is completely covered
+const SETTLE_TIME: f32 = 10.0;
+/// Seconds indoors until a completely covered coat has melted clean
+const MELT_TIME: f32 = 8.0;
+/// Flakes drawn on a completely covered coat
+const MAX_SETTLED_FLAKES: usize = 16;
+const SETTLED_SEED: u64 = 7;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ParticleKind {
+    Snow,
+    Steam,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Particle {
+    pub kind: ParticleKind,
+    /// World position
+    pub pos: Vec2,
+    /// Own velocity, before wind and sway
+    pub vel: Vec2,
+    pub age: f32,
+    pub life: f32,
+    /// Size in pixels. Bigger snowflakes are closer, so the wind pushes them harder.
+    pub size: f32,
+    /// Offset of the particle's sideways sway, so they don't all sway together
+    pub phase: f32,
+}
+impl Particle {
+    /// Opacity, fading in after spawning and out before dying
+    pub fn alpha(&self) -> f32 {
+        let max = match self.kind {
+            ParticleKind::Snow => 0.8,
+            ParticleKind::Steam => 0.3,
+        };
+        max * (self.age / 0.3).min(1.0) * ((self.life - self.age) / 0.5).clamp(0.0, 1.0)
+    }
+}
+
+/// The storm outside and everything else floating about
+pub struct Weather {
+    rng: Rng,
+    pub particles: Vec<Particle>,
+    /// Storm strength from 0 (calm) to 1 (blizzard), easing towards `target_intensity`
+    pub intensity: f32,
+    pub target_intensity: f32,
+    /// Wind multiplier added by the current gust
+    pub gust: f32,
+    gust_target: f32,
+    gust_timer: f32,
+    // fractions of a particle left over from previous frames, so low rates still spawn some
+    snow_owed: f32,
+    steam_owed: f32,
+}
+impl Weather {
+    pub fn new(seed: u64, intensity: f32) -> Self {
+        Self {
+            rng: Rng::new(seed),
+            particles: Vec::new(),
+            intensity,
+            target_intensity: intensity,
+            gust: 0.0,
+            gust_target: 0.0,
+            gust_timer: 0.0,
+            snow_owed: 0.0,
+            steam_owed: 0.0,
+        }
+    }
+    /// Sideways wind speed right now, in pixels per second
+    pub fn wind(&self) -> f32 {
+        BASE_WIND * self.intensity * (1.0 + self.gust)
+    }
+    /// Steps the weather forward. Snow spawns across `view`, the part of the world on screen,
+    /// and steam rises from every point in `steam_sources`.
+    pub fn update(&mut self, delta_time: f32, view: Rect, steam_sources: &[Vec2]) {
+        self.intensity = move_towards(
+            self.intensity,
+            self.target_intensity,
+            delta_time * INTENSITY_SPEED,
+        );
+
+        self.gust_timer -= delta_time;
+        if self.gust_timer <= 0.0 {
+            self.gust_target = if self.gust_target > 0.0 {
+                0.0
+            } else {
+                self.rng.range(GUST_STRENGTH)
+            };
+            self.gust_timer = self.rng.range(GUST_INTERVAL);
+        }
+        self.gust += (self.gust_target - self.gust) * (delta_time * 2.0).min(1.0);
+
+        self.snow_owed += SNOW_RATE * self.intensity * delta_time;
+        while self.snow_owed >= 1.0 {
+            self.snow_owed -= 1.0;
+            // spawn anywhere on screen and fade in, so the wind never leaves a side empty
+            let pos = vec2(
+                view.x + self.rng.range((-16.0, view.w + 16.0)),
+                view.y + self.rng.range((-16.0, view.h)),
+            );
+            let size: f32 = if self.rng.next_f32() < 0.25 { 2.0 } else { 1.0 };
+            let particle = Particle {
+                kind: ParticleKind::Snow,
+                pos,
+                vel: vec2(0.0, self.rng.range(FALL_SPEED) * size.sqrt()),
+                age: 0.0,
+                life: self.rng.range(SNOW_LIFE),
+                size,
+                phase: self.rng.range((0.0, std::f32::consts::TAU)),
+            };
+            self.particles.push(particle);
+        }
+
+        self.steam_owed += STEAM_RATE * delta_time;
+        while self.steam_owed >= 1.0 {
+            self.steam_owed -= 1.0;
+            for source in steam_sources {
+                let particle = Particle {
+                    kind: ParticleKind::Steam,
+                    pos: *source + vec2(self.rng.range((-2.0, 2.0)), 0.0),
+                    vel: vec2(0.0, -self.rng.range((6.0, 12.0))),
+                    age: 0.0,
+                    life: self.rng.range(STEAM_LIFE),
+                    size: 2.0,
+                    phase: self.rng.range((0.0, std::f32::consts::TAU)),
+                };
+                self.particles.push(particle);
+            }
+        }
+
+        let wind = self.wind();
+        for particle in self.particles.iter_mut() {
+            particle.age += delta_time;
+            let sway = (particle.age * 2.0 + particle.phase).sin();
+            let drift = match particle.kind {
+                ParticleKind::Snow => wind * particle.size.sqrt() + sway * 6.0,
+                // steam is only ever indoors, out of the wind
+                ParticleKind::Steam => sway * 3.0,
+            };
+            particle.pos += (particle.vel + vec2(drift, 0.0)) * delta_time;
+        }
+        self.particles.retain(|f| f.age < f.life);
+    }
+    /// Draws every particle of one kind, with the world's top left corner at `origin` on screen
+    pub fn draw(&self, kind: ParticleKind, origin: Vec2) {
+        for particle in self.particles.iter().filter(|f| f.kind == kind) {
+            // steam spreads out as it rises
+            let size = match kind {
+                ParticleKind::Snow => particle.size,
+                ParticleKind::Steam => particle.size + particle.age * 2.0,
+            };
+            draw_rectangle(
+                (origin.x + particle.pos.x - size / 2.0).floor(),
+                (origin.y + particle.pos.y - size / 2.0).floor(),
+                size,
+                size,
+                WHITE.with_alpha(particle.alpha()),
+            );
+        }
+    }
+}
+
+fn move_towards(value: f32, target: f32, amount: f32) -> f32 {
+    if value < target {
+        (value + amount).min(target)
+    } else {
+        (value - amount).max(target)
+    }
+}
+
+/// Builds up snow on a character's coat while it stands somewhere without a floor,
+/// and melts it away once it's inside
+pub fn settle_snow(character: &mut Character, map: &Map, intensity: f32, delta_time: f32) {
+    let exposed = map.floor.0[character.x + character.y * map.floor.1] == 0;
+    character.snow = snow_after(character.snow, exposed, intensity, delta_time);
+}
+
+/// How covered a coat is after another `delta_time` out in the storm, or inside if not `exposed`
+fn snow_after(snow: f32, exposed: bool, intensity: f32, delta_time: f32) -> f32 {
+    if exposed {
+        snow + intensity * delta_time / SETTLE_TIME
+    } else {
+        snow - delta_time / MELT_TIME
+    }
+    .clamp(0.0, 1.0)
+}
+
+/// Where each flake sits on a coat with this much snow, relative to the character's `draw_pos`
+fn settled_flakes(snow: f32) -> Vec<Vec2> {
+    // the same seed every time, so flakes stay put and new ones appear as snow builds up
+    let mut rng = Rng::new(SETTLED_SEED);
+    let count = (snow * MAX_SETTLED_FLAKES as f32) as usize;
+    (0..count)
+        // roughly the head and shoulders of a ferret standing on the tile
+        .map(|_| vec2(rng.range((3.0, 13.0)), rng.range((-6.0, 8.0))))
+        .collect()
+}
+
+/// Draws flakes stuck to a character's coat, more of them the more snow has settled
+pub fn draw_settled_snow(character: &Character, ctx: &mut DrawCtx) {
+    let origin = (-ctx.camera_pos * ctx.scale_factor
+        + vec2(SCREEN_WIDTH, SCREEN_HEIGHT) * ctx.scale_factor / 2.0)
+        .floor();
+    for offset in settled_flakes(character.snow) {
+        let pos = character.draw_pos + offset;
+        ctx.draw_rectangle(
+            (origin.x + pos.x.floor() * ctx.scale_factor).floor(),
+            (origin.y + pos.y.floor() * ctx.scale_factor).floor(),
+            ctx.scale_factor,
+            ctx.scale_factor,
+            WHITE.with_alpha(0.9),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs the storm for a while with uneven frame times, getting stronger halfway through,
+    /// and returns the particles left and the flakes settled on a coat out in it
+    fn run(seed: u64) -> (Vec<Particle>, Vec<Vec2>) {
+        let view = Rect::new(0.0, 0.0, 256.0, 144.0);
+        let mut weather = Weather::new(seed, 0.4);
+        let mut snow = 0.0;
+        for step in 0..400 {
+            if step == 200 {
+                weather.target_intensity = 1.0;
+            }
+            let delta_time = [0.016, 0.033, 0.008][step % 3];
+            weather.update(delta_time, view, &[vec2(40.0, 60.0)]);
+            snow = snow_after(snow, true, weather.intensity, delta_time);
+        }
+        (weather.particles, settled_flakes(snow))
+    }
+
+    #[test]
+    fn same_seed_same_weather() {
+        let (particles, flakes) = run(42);
+        assert!(!particles.is_empty() && !flakes.is_empty());
+        assert_eq!((particles, flakes), run(42));
+    }
+
+    #[test]
+    fn different_seeds_differ() {
+        assert_ne!(run(1).0, run(2).0);
+    }
+}
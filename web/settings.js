@@ -0,0 +1,33 @@
+// keeps the game's settings in the browser's local storage, for LocalStorage in src/settings.rs
+const SETTINGS_KEY = "winternight_settings";
+
+function settings_bytes() {
+    // local storage can be turned off, which is the same as there being nothing saved
+    try {
+        return new TextEncoder().encode(localStorage.getItem(SETTINGS_KEY) ?? "");
+    } catch (err) {
+        return new Uint8Array();
+    }
+}
+
+miniquad_add_plugin({
+    name: "winternight_settings",
+    version: 1,
+    register_plugin: function (importObject) {
+        importObject.env.settings_length = function () {
+            return settings_bytes().length;
+        };
+        importObject.env.settings_read = function (ptr) {
+            const bytes = settings_bytes();
+            new Uint8Array(wasm_memory.buffer, ptr, bytes.length).set(bytes);
+        };
+        importObject.env.settings_write = function (ptr, len) {
+            const text = new TextDecoder().decode(new Uint8Array(wasm_memory.buffer, ptr, len));
+            try {
+                localStorage.setItem(SETTINGS_KEY, text);
+            } catch (err) {
+                console.error("couldn't save settings: " + err);
+            }
+        };
+    },
+});